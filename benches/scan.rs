@@ -0,0 +1,99 @@
+// Times scanning wiki/source.txt through Regex::accept, the path the driver
+// takes for every char, against the same match walked over a Vec of rows
+// and a BTreeMap alphabet, the way DFAs were kept before the flat table.
+// Run with cargo bench.
+
+use luther::dfa::DFA;
+use luther::driver::Driver;
+use luther::regex::Regex;
+use luther::scanner::{Alphabet, Scanner};
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const REPEAT: usize = 2000;
+
+/// A DFA as a Vec of rows with a BTreeMap alphabet
+struct Rows {
+    alpha: BTreeMap<char, usize>,
+    rows: Vec<(bool, Vec<Option<usize>>)>,
+}
+
+impl Rows {
+    fn new(dfa: &DFA, alpha: &Alphabet) -> Self {
+        Self {
+            alpha: alpha.iter().map(|(&c, &i)| (c, i)).collect(),
+            rows: (0..dfa.states())
+                .map(|r| {
+                    let row = (0..dfa.columns()).map(|c| dfa.transition(r, c)).collect();
+                    (dfa.is_accepting(r), row)
+                })
+                .collect(),
+        }
+    }
+
+    /// The longest match and the line and column after it, as first_match
+    /// keeps track of them
+    fn first_match(&self, input: &str, newline: char) -> (usize, usize, usize) {
+        let (mut state, mut length) = (0, 0);
+        let (mut newlines, mut position) = (0, 1);
+        let mut found = (0, 0, 1);
+        for letter in input.chars() {
+            let next = self
+                .alpha
+                .get(&letter)
+                .and_then(|&column| self.rows[state].1[column]);
+            match next {
+                Some(next) => state = next,
+                None => break,
+            }
+            length += 1;
+            position += 1;
+            if letter == newline {
+                newlines += 1;
+                position = 1;
+            }
+            if self.rows[state].0 {
+                found = (length, newlines, position);
+            }
+        }
+        found
+    }
+}
+
+fn time(name: &str, chars: usize, mut f: impl FnMut()) {
+    f();
+    let start = Instant::now();
+    let mut runs = 0;
+    while start.elapsed() < Duration::from_secs(1) {
+        f();
+        runs += 1;
+    }
+    let per_char = start.elapsed().as_nanos() as f64 / (runs * chars) as f64;
+    println!("{:<24} {:>8.2} ns/char", name, per_char);
+}
+
+fn main() {
+    let scanner = Scanner::from_file("wiki/scan.u").unwrap();
+    let source = std::fs::read_to_string("wiki/source.txt")
+        .unwrap()
+        .repeat(REPEAT);
+
+    time("tokenize", source.chars().count(), || {
+        black_box(Driver::tokenize(&scanner, black_box(&source)).unwrap());
+    });
+
+    // pqrs takes every letter but o, so its match runs through the whole text
+    let alpha = scanner.get_alpha();
+    let pqrs = &scanner.get_trans()[0];
+    let regex = Regex::new(&pqrs.tt, Some(&pqrs.id), &alpha, None);
+    let letters: String = source.chars().filter(|c| "pqrs".contains(*c)).collect();
+    time("pqrs first_match", letters.len(), || {
+        black_box(regex.first_match(black_box(&letters), '\n'));
+    });
+    let rows = Rows::new(&pqrs.tt, &alpha);
+    assert_eq!(rows.first_match(&letters, '\n').0, letters.len());
+    time("pqrs first_match (rows)", letters.len(), || {
+        black_box(rows.first_match(black_box(&letters), '\n'));
+    });
+}
@@ -0,0 +1,175 @@
+use std::collections::btree_map::{IntoIter, Iter};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::Index;
+
+/// Which transition table column each char is read as. An alphabet line
+/// with [:other:] gets a column for every char it doesn't list, so those
+/// chars are scanned like any other instead of being refused.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Alphabet {
+    letters: BTreeMap<char, usize>,
+    other: Option<usize>,
+    /// The column of each ASCII char, so most lookups skip the map
+    ascii: [Option<usize>; 128],
+}
+
+impl Alphabet {
+    pub fn new() -> Self {
+        Self {
+            letters: BTreeMap::new(),
+            other: None,
+            ascii: [None; 128],
+        }
+    }
+
+    pub fn insert(&mut self, letter: char, column: usize) -> Option<usize> {
+        if letter.is_ascii() {
+            self.ascii[letter as usize] = Some(column);
+        }
+        self.letters.insert(letter, column)
+    }
+
+    /// Makes column the one for every char not listed
+    pub fn set_other(&mut self, column: usize) {
+        self.other = Some(column);
+    }
+
+    /// The column for chars not listed, if there is one
+    pub fn other(&self) -> Option<usize> {
+        self.other
+    }
+
+    /// The column of a char, the other column if it isn't listed
+    pub fn get(&self, letter: &char) -> Option<&usize> {
+        let listed = if letter.is_ascii() {
+            self.ascii[*letter as usize].as_ref()
+        } else {
+            self.letters.get(letter)
+        };
+        listed.or(self.other.as_ref())
+    }
+
+    /// A char that stands for the other column, the first printable one not
+    /// listed
+    pub fn other_letter(&self) -> Option<char> {
+        self.other?;
+        ('!'..='~')
+            .chain('\u{a1}'..=char::MAX)
+            .find(|c| !self.is_listed(c))
+    }
+
+    /// Can the char be scanned at all
+    pub fn contains_key(&self, letter: &char) -> bool {
+        self.get(letter).is_some()
+    }
+
+    /// Is the char listed on its own, rather than falling into the other column
+    pub fn is_listed(&self, letter: &char) -> bool {
+        self.letters.contains_key(letter)
+    }
+
+    /// The listed chars and their columns, in char order
+    pub fn iter(&self) -> Iter<'_, char, usize> {
+        self.letters.iter()
+    }
+
+    /// The ASCII chars of a named class such as [:digit:], by its name
+    pub fn class(name: &str) -> Option<Vec<char>> {
+        let test: fn(&char) -> bool = match name {
+            "digit" => char::is_ascii_digit,
+            "xdigit" => char::is_ascii_hexdigit,
+            "lower" => char::is_ascii_lowercase,
+            "upper" => char::is_ascii_uppercase,
+            "alpha" => char::is_ascii_alphabetic,
+            "alnum" => char::is_ascii_alphanumeric,
+            "punct" => char::is_ascii_punctuation,
+            "space" => char::is_ascii_whitespace,
+            "print" => |c| (' '..='~').contains(c),
+            _ => return None,
+        };
+        Some((0..128u8).map(char::from).filter(test).collect())
+    }
+
+    /// The number of columns, counting the other one
+    pub fn columns(&self) -> usize {
+        self.letters
+            .values()
+            .chain(self.other.iter())
+            .max()
+            .map_or(0, |c| c + 1)
+    }
+}
+
+impl Default for Alphabet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Alphabet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Alphabet")
+            .field("letters", &self.letters)
+            .field("other", &self.other)
+            .finish()
+    }
+}
+
+impl Index<&char> for Alphabet {
+    type Output = usize;
+
+    fn index(&self, letter: &char) -> &usize {
+        self.get(letter).expect("char not in the alphabet")
+    }
+}
+
+impl FromIterator<(char, usize)> for Alphabet {
+    fn from_iter<I: IntoIterator<Item = (char, usize)>>(iter: I) -> Self {
+        let mut alpha = Self::new();
+        for (letter, column) in iter {
+            alpha.insert(letter, column);
+        }
+        alpha
+    }
+}
+
+impl IntoIterator for Alphabet {
+    type Item = (char, usize);
+    type IntoIter = IntoIter<char, usize>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.letters.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Alphabet {
+    type Item = (&'a char, &'a usize);
+    type IntoIter = Iter<'a, char, usize>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.letters.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn other_takes_what_is_not_listed() {
+        let mut alpha: Alphabet = vec![('a', 0), ('b', 1)].into_iter().collect();
+        assert_eq!(alpha.get(&'z'), None);
+        assert_eq!(alpha.columns(), 2);
+
+        alpha.set_other(2);
+        assert_eq!(alpha[&'a'], 0);
+        assert_eq!(alpha[&'z'], 2);
+        assert_eq!(alpha[&'ü'], 2);
+        assert!(alpha.contains_key(&'z') && !alpha.is_listed(&'z'));
+        assert_eq!(alpha.columns(), 3);
+        assert_eq!(alpha.iter().count(), 2);
+        assert_eq!(alpha.other_letter(), Some('!'));
+    }
+}
@@ -25,6 +25,43 @@ pub mod alphabet_translator {
         }
         temp
     }
+
+    /// Inverse of `char_to_hex_a_string`, except that plain characters may be
+    /// mixed in with the xHH codes, e.g. `x5cooox5c` becomes `\ooo\`.
+    pub fn hex_a_string_to_string(input: &str) -> String {
+        let chars: Vec<char> = input.chars().collect();
+        let mut temp = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] == 'x' && i + 2 < chars.len() && is_hex_pair(&chars[i + 1..=i + 2]) {
+                let hex_str: String = chars[i + 1..=i + 2].iter().collect();
+                temp.push(hex_to_char(&hex_str));
+                i += 3;
+            } else {
+                temp.push(chars[i]);
+                i += 1;
+            }
+        }
+        temp
+    }
+
+    /// Like `char_to_hex_a_string`, but printable characters other than x are
+    /// left alone, so `hex_a_string_to_string` turns it back into the input.
+    pub fn readable_hex_a_string(input: &str) -> String {
+        let mut temp = String::new();
+        for c in input.chars() {
+            if c.is_ascii_graphic() && c != 'x' {
+                temp.push(c);
+            } else {
+                temp.push_str(&char_to_hex(c));
+            }
+        }
+        temp
+    }
+
+    fn is_hex_pair(pair: &[char]) -> bool {
+        pair.iter().all(|c| c.is_ascii_hexdigit())
+    }
 }
 
 #[cfg(test)]
@@ -77,6 +114,29 @@ mod test {
         assert_eq!('\n', alphabet_translator::hex_to_char("0A"));
     }
 
+    #[test]
+    fn mixed_str_from_hex() {
+        assert_eq!(
+            "\\ooo\\ \\ooo\\",
+            alphabet_translator::hex_a_string_to_string("x5cooox5cx20x5cooox5c")
+        );
+    }
+
+    #[test]
+    fn plain_x_from_hex() {
+        assert_eq!("max", alphabet_translator::hex_a_string_to_string("max"));
+    }
+
+    #[test]
+    fn readable_str_trans() {
+        let readable = alphabet_translator::readable_hex_a_string("\\ox o\n");
+        assert_eq!("\\ox78x20ox0A", readable);
+        assert_eq!(
+            "\\ox o\n",
+            alphabet_translator::hex_a_string_to_string(&readable)
+        );
+    }
+
     #[test]
     fn wack_hex() {
         assert_eq!('\\', alphabet_translator::hex_to_char("5C"));
@@ -0,0 +1,330 @@
+use crate::dfa::DFA;
+use crate::driver::{Driver, MatchPolicy};
+use crate::product::Product;
+use crate::scanner::Scanner;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Two transition tables that accept some of the same text, found by
+/// running their DFAs side by side.
+/// example is the shortest such text, as alphabet columns
+/// winner is the one of the two the driver picks on the example, under the
+/// scanner's policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overlap {
+    pub first: usize,
+    pub second: usize,
+    pub example: Vec<usize>,
+    pub winner: usize,
+}
+
+/// Finds every pair of transition tables that can match the same non-empty text
+pub fn overlaps(scanner: &Scanner) -> Vec<Overlap> {
+    let trans = scanner.get_trans();
+    let mut found = Vec::new();
+
+    for first in 0..trans.len() {
+        for second in first + 1..trans.len() {
+            let product = Product::new(vec![&trans[first].tt, &trans[second].tt]);
+            if let Some(example) = product.shortest_nonempty_word(|a| a[0] && a[1]) {
+                let shortest = scanner.policy == MatchPolicy::Shortest;
+                let pair = [first, second];
+                let matches = pair.iter().filter_map(|&table| {
+                    trans[table]
+                        .tt
+                        .prefix_length(&example, shortest)
+                        .map(|length| (table, length))
+                });
+                let (winner, _) = Driver::pick(trans, scanner.policy, matches)
+                    .expect("both tables match the example");
+                found.push(Overlap {
+                    first,
+                    second,
+                    example,
+                    winner,
+                });
+            }
+        }
+    }
+
+    found
+}
+
+/// Finds the transition tables that can never produce a token under the
+/// scanner's policy, because on every text they match some other table wins:
+/// under maximal munch a table that wins ties matches all of it, under first
+/// defined a table that wins ties matches some of its start, and under
+/// shortest another table matches less of it or a table that wins ties
+/// matches just as much. Tables that match no non-empty text at all are not
+/// included.
+pub fn shadowed(scanner: &Scanner) -> Vec<usize> {
+    let trans = scanner.get_trans();
+    let columns = trans.iter().map(|t| t.tt.columns()).max().unwrap_or(0);
+    let letter = DFA::from_table(
+        vec![false, true],
+        vec![vec![Some(1); columns], vec![None; columns]],
+    );
+    let nonempty = letter.plus();
+    // texts with a non-empty start the DFA matches, and ones with a shorter one
+    let starts = |dfa: &DFA| dfa.intersection(&nonempty).concat(&letter.star());
+    let proper_starts = |dfa: &DFA| dfa.intersection(&nonempty).concat(&nonempty);
+    let union = |dfas: Vec<DFA>| dfas.iter().fold(DFA::default(), |all, dfa| all.union(dfa));
+
+    let mut found = Vec::new();
+    for shadow in 0..trans.len() {
+        let wins_ties = |t: usize| Driver::rank(trans, t) > Driver::rank(trans, shadow);
+        let winners = (0..trans.len())
+            .filter(|&t| wins_ties(t))
+            .map(|t| &trans[t].tt);
+        let texts = trans[shadow].tt.intersection(&nonempty);
+        let (texts, beaten) = match scanner.policy {
+            MatchPolicy::MaximalMunch => (texts, union(winners.cloned().collect())),
+            MatchPolicy::FirstDefined => (texts, union(winners.map(starts).collect())),
+            MatchPolicy::Shortest => {
+                let others = (0..trans.len())
+                    .filter(|&t| t != shadow)
+                    .map(|t| proper_starts(&trans[t].tt));
+                // Only the shortest match of the table itself counts
+                let shortest = texts.difference(&proper_starts(&texts));
+                (shortest, union(winners.cloned().chain(others).collect()))
+            }
+        };
+
+        if !texts.is_empty() && texts.is_subset_of(&beaten).is_ok() {
+            found.push(shadow);
+        }
+    }
+
+    found
+}
+
+/// A text whose first token is not the same with two scanners.
+/// old and new are the (table, length) of the first token each of them
+/// makes of the text, None when it has no token at all
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    pub example: Vec<usize>,
+    pub old: Option<(usize, usize)>,
+    pub new: Option<(usize, usize)>,
+}
+
+/// The keywords of each table as words of alphabet columns, with their
+/// ids. Keywords with chars the alphabet can't scan never match and are left
+/// out.
+fn keyword_words(scanner: &Scanner) -> Vec<BTreeMap<Vec<usize>, &str>> {
+    let alpha = scanner.get_alpha();
+    scanner
+        .get_trans()
+        .iter()
+        .map(|t| {
+            t.keywords
+                .iter()
+                .flat_map(|k| k.iter())
+                .filter_map(|(lexeme, id)| {
+                    let word: Option<Vec<usize>> =
+                        lexeme.chars().map(|c| alpha.get(&c).copied()).collect();
+                    Some((word?, id))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The first token the scanner makes of the word, picked the way the driver
+/// does, as (table, length, id)
+fn first_token<'s>(
+    scanner: &'s Scanner,
+    keywords: &[BTreeMap<Vec<usize>, &'s str>],
+    word: &[usize],
+) -> Option<(usize, usize, &'s str)> {
+    let trans = scanner.get_trans();
+    let shortest = scanner.policy == MatchPolicy::Shortest;
+    let matches = trans.iter().enumerate().filter_map(|(table, t)| {
+        t.tt.prefix_length(word, shortest)
+            .map(|length| (table, length))
+    });
+    let (table, length) = Driver::pick(trans, scanner.policy, matches)?;
+    let id = match keywords[table].get(&word[..length]) {
+        Some(keyword) => keyword,
+        None => trans[table].id.as_str(),
+    };
+    Some((table, length, id))
+}
+
+/// Finds texts the two scanners start tokenizing differently, the shortest
+/// for each pair of token ids they disagree on. The scanners must share an
+/// alphabet. One search runs the tables and keyword tables of both side by
+/// side, and the shortest text reaching each of their combined states is
+/// split with each scanner's own policy.
+pub fn differences(old: &Scanner, new: &Scanner) -> Vec<Difference> {
+    let (old_keywords, new_keywords) = (keyword_words(old), keyword_words(new));
+    let keyword_dfas: Vec<DFA> = keyword_dfas(old)
+        .into_iter()
+        .chain(keyword_dfas(new))
+        .collect();
+    let dfas = old
+        .get_trans()
+        .iter()
+        .chain(new.get_trans().iter())
+        .map(|t| &t.tt)
+        .chain(keyword_dfas.iter())
+        .collect();
+    let product = Product::new(dfas);
+
+    let mut pairs = BTreeSet::new();
+    let mut found: Vec<Difference> = vec![];
+    product.visit(|_, word| {
+        let old_token = first_token(old, &old_keywords, word);
+        let new_token = first_token(new, &new_keywords, word);
+        let same = old_token.map(|(_, length, id)| (id, length))
+            == new_token.map(|(_, length, id)| (id, length));
+        let ids = (old_token.map(|t| t.2), new_token.map(|t| t.2));
+        if !same && pairs.insert(ids) {
+            found.push(Difference {
+                example: word.to_vec(),
+                old: old_token.map(|(t, length, _)| (t, length)),
+                new: new_token.map(|(t, length, _)| (t, length)),
+            });
+        }
+    });
+
+    found.sort_by(|a, b| (a.example.len(), &a.example).cmp(&(b.example.len(), &b.example)));
+    found
+}
+
+/// DFAs accepting exactly the keywords of each table that has some, so the
+/// search tells them apart from other texts
+fn keyword_dfas(scanner: &Scanner) -> Vec<DFA> {
+    let alpha = scanner.get_alpha();
+    scanner
+        .get_trans()
+        .iter()
+        .filter_map(|t| t.keywords.as_ref())
+        .filter_map(|k| {
+            let lexemes: Vec<&str> = k
+                .iter()
+                .map(|(lexeme, _)| lexeme)
+                .filter(|lexeme| lexeme.chars().all(|c| alpha.contains_key(&c)))
+                .collect();
+            DFA::from_keywords(&lexemes, &alpha).ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scanner::TransitionTable;
+
+    #[test]
+    fn wiki_overlaps() {
+        let sc = Scanner::from_file("wiki/scan.u").unwrap();
+        let found = overlaps(&sc);
+        let pairs: Vec<(usize, usize)> = found.iter().map(|o| (o.first, o.second)).collect();
+        // pqrs, opqr, endsq and IGNORE all accept single letters
+        assert_eq!(pairs, vec![(0, 1), (0, 2), (0, 5), (1, 2), (1, 5), (2, 5)]);
+        assert_eq!(sc.word_to_string(&found[0].example), "p");
+        assert_eq!(found[0].winner, 0);
+        assert_eq!(sc.word_to_string(&found[1].example), "q");
+    }
+
+    #[test]
+    fn overlap_winner_follows_policy() {
+        let wiki = Scanner::from_file("wiki/scan.u").unwrap();
+        let alpha = wiki.get_alpha();
+        let trans = vec![
+            TransitionTable::new(
+                DFA::from_keywords(&["pp"], &alpha).unwrap(),
+                "pp".into(),
+                None,
+            ),
+            TransitionTable::new(DFA::from_file("wiki/noto.tt").unwrap(), "pqrs".into(), None),
+        ];
+        let mut sc = Scanner::new(wiki.newline, alpha, trans);
+        assert_eq!(sc.word_to_string(&overlaps(&sc)[0].example), "pp");
+        // pp is listed first and both match all of it
+        assert_eq!(overlaps(&sc)[0].winner, 0);
+        // but pqrs is done after the first p
+        sc.policy = MatchPolicy::Shortest;
+        assert_eq!(overlaps(&sc)[0].winner, 1);
+    }
+
+    #[test]
+    fn wiki_has_no_shadowed_tables() {
+        let sc = Scanner::from_file("wiki/scan.u").unwrap();
+        assert_eq!(shadowed(&sc), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn superset_listed_first_shadows() {
+        let sc = Scanner::from_file("tests/shadowed.u").unwrap();
+        // pqrs matches everything pq does and is listed first
+        assert_eq!(shadowed(&sc), vec![1]);
+        let winners: Vec<usize> = overlaps(&sc).iter().map(|o| o.winner).collect();
+        assert_eq!(winners, vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn shadowed_under_each_policy() {
+        let mut sc = Scanner::from_file("wiki/scan.u").unwrap();
+        sc.policy = MatchPolicy::FirstDefined;
+        // o starts every text of twosmallwords, and opqr comes first
+        assert_eq!(shadowed(&sc), vec![3]);
+        sc.policy = MatchPolicy::Shortest;
+        // some table comes first with as much of each endsq text, or takes
+        // less of it, and IGNORE takes one char of twosmallwords and whack
+        assert_eq!(shadowed(&sc), vec![2, 3, 4]);
+        let sc = Scanner::from_file("tests/priorities.u").unwrap();
+        assert_eq!(shadowed(&sc), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn same_scanner_no_differences() {
+        let sc = Scanner::from_file("wiki/scan.u").unwrap();
+        assert_eq!(differences(&sc, &sc), vec![]);
+    }
+
+    #[test]
+    fn wiki_against_shadowed() {
+        let old = Scanner::from_file("wiki/scan.u").unwrap();
+        let new = Scanner::from_file("tests/shadowed.u").unwrap();
+        let found = differences(&old, &new);
+        let shown: Vec<String> = found
+            .iter()
+            .map(|d| old.word_to_string(&d.example))
+            .collect();
+        // opqr, endsq, whack and twosmallwords are gone, IGNORE takes one char instead
+        // oo is left out, opqr against IGNORE is already shown by o
+        assert_eq!(shown, vec!["o", "x0Aq", "\\x20\\", "ooox20ooox20"]);
+        assert_eq!(found[0].old, Some((1, 1)));
+        assert_eq!(found[2].old, Some((4, 3)));
+        assert!(found.iter().all(|d| d.new == Some((2, 1))));
+    }
+
+    #[test]
+    fn keywords_make_differences() {
+        let old = Scanner::from_file("tests/keywords.u").unwrap();
+        let trans = old
+            .get_trans()
+            .iter()
+            .map(|t| TransitionTable::new(t.tt.clone(), t.id.clone(), None))
+            .collect();
+        let new = Scanner::new(old.newline, old.get_alpha(), trans);
+        let shown: Vec<String> = differences(&old, &new)
+            .iter()
+            .map(|d| old.word_to_string(&d.example))
+            .collect();
+        // pop has an o in it, which pqrs never matches
+        assert_eq!(shown, vec!["pqrs"]);
+    }
+
+    #[test]
+    fn policies_make_differences() {
+        let old = Scanner::from_file("wiki/scan.u").unwrap();
+        let mut new = Scanner::from_file("wiki/scan.u").unwrap();
+        new.policy = MatchPolicy::Shortest;
+        let found = differences(&old, &new);
+        // endsq takes both chars, shortest leaves IGNORE the newline
+        assert_eq!(old.word_to_string(&found[0].example), "x0Aq");
+        assert_eq!((found[0].old, found[0].new), (Some((2, 2)), Some((5, 1))));
+    }
+}
@@ -1,18 +1,54 @@
 #![allow(non_snake_case)]
 
+use luther::alphabet_translator::alphabet_translator;
+use luther::analysis;
+use luther::coverage::Coverage;
 use luther::dfa::*;
 use luther::driver::*;
+use luther::expr::Expr;
+use luther::fuzz::Fuzzer;
+use luther::golden::{self, Case, Change};
+use luther::random::Random;
 use luther::regex::*;
+use luther::repl;
 use luther::scanner::*;
+use structopt::clap::{AppSettings, Error, ErrorKind};
 use structopt::StructOpt;
 
-use std::path::PathBuf;
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 
 /// LUTHER Reads a scanner definition file, a source file, and outputs matched
 /// tokens from DFA files described in the definition input.
 #[derive(Debug, Default, StructOpt)]
-#[structopt(name = "LUTHER")]
+#[structopt(name = "LUTHER", setting = AppSettings::SubcommandsNegateReqs)]
 pub struct Args {
+    /// Path to the scanning definition file
+    #[structopt(parse(from_os_str))]
+    definition: Option<PathBuf>,
+
+    /// Path to a file of source to be tokenized by LUTHER
+    #[structopt(parse(from_os_str))]
+    source: Option<PathBuf>,
+
+    /// Path to an output file for storing the tokenized source. Stdout if not present.
+    #[structopt(parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    trace: Trace,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+/// The plain run, parsed on its own when the first argument is a file. Clap
+/// would otherwise take a definition such as tests/x.u for a misspelt `test`.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "LUTHER")]
+pub struct Run {
     /// Path to the scanning definition file
     #[structopt(parse(from_os_str))]
     definition: PathBuf,
@@ -21,16 +57,821 @@ pub struct Args {
     #[structopt(parse(from_os_str))]
     source: PathBuf,
 
-    /// Path to an output file for storing the tokenized source. Stdout if not present.
+    /// Path to an output file for storing the tokenized source
     #[structopt(parse(from_os_str))]
     output: PathBuf,
+
+    #[structopt(flatten)]
+    trace: Trace,
+}
+
+#[derive(Debug, Default, StructOpt)]
+pub struct Trace {
+    /// Write why each token was picked to stderr
+    #[structopt(long)]
+    trace: bool,
+
+    /// Write why each token was picked to this file instead of stderr
+    #[structopt(long, parse(from_os_str))]
+    trace_file: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Builds a minimal DFA accepting exactly the keywords listed in a file
+    Keywords {
+        /// Path to a file with one keyword per line, using xHH for special chars
+        #[structopt(parse(from_os_str))]
+        words: PathBuf,
+
+        /// The alphabet line of the scanning definition the DFA is for
+        #[structopt(short, long)]
+        alphabet: String,
+
+        /// Path to an output file for storing the .tt file. Stdout if not present.
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Writes the .tt file for an expression over .tt files, like a&!b
+    Build {
+        /// Expression combining .tt files with | & ! ~ * + ?, concatenation and parentheses
+        expression: String,
+
+        /// The alphabet line of the scanning definition, complements are taken over its columns
+        #[structopt(short, long)]
+        alphabet: String,
+
+        /// Path to an output file for storing the .tt file. Stdout if not present.
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Checks that two .tt files accept the same texts
+    DiffDfa {
+        /// Path to the first .tt file
+        #[structopt(parse(from_os_str))]
+        first: PathBuf,
+
+        /// Path to the second .tt file
+        #[structopt(parse(from_os_str))]
+        second: PathBuf,
+
+        /// The alphabet line of the scanning definition the DFAs are for
+        #[structopt(short, long)]
+        alphabet: String,
+    },
+
+    /// Reports how two scanning definitions tokenize differently
+    DiffScanner {
+        /// Path to the old scanning definition file
+        #[structopt(parse(from_os_str))]
+        old: PathBuf,
+
+        /// Path to the new scanning definition file
+        #[structopt(parse(from_os_str))]
+        new: PathBuf,
+
+        /// Paths to sources to tokenize with both and compare
+        #[structopt(short, long, parse(from_os_str))]
+        corpus: Vec<PathBuf>,
+    },
+
+    /// Lists the shortest texts a .tt file accepts
+    Examples {
+        /// Path to the .tt file
+        #[structopt(parse(from_os_str))]
+        table: PathBuf,
+
+        /// The alphabet line of the scanning definition the DFA is for
+        #[structopt(short, long)]
+        alphabet: String,
+
+        /// How many texts to list at most
+        #[structopt(short, default_value = "20")]
+        n: usize,
+
+        /// List texts that take every transition instead, with whether each is accepted
+        #[structopt(short, long)]
+        covering: bool,
+    },
+
+    /// Counts the texts of a length a .tt file accepts and picks some at random
+    Sample {
+        /// Path to the .tt file
+        #[structopt(parse(from_os_str))]
+        table: PathBuf,
+
+        /// The alphabet line of the scanning definition the DFA is for
+        #[structopt(short, long)]
+        alphabet: String,
+
+        /// Length of the texts
+        #[structopt(short, long)]
+        length: usize,
+
+        /// How many texts to pick
+        #[structopt(short, default_value = "10")]
+        n: usize,
+
+        /// Seed for the random picks, the same seed gives the same texts
+        #[structopt(short, long, default_value = "0")]
+        seed: u64,
+    },
+
+    /// Tokenizes random sources built from the definition's tables and
+    /// compares the tokens with the ones the sources were built from
+    Fuzz {
+        /// Path to the scanning definition file
+        #[structopt(parse(from_os_str))]
+        definition: PathBuf,
+
+        /// How many tokens each source has
+        #[structopt(short, default_value = "50")]
+        n: usize,
+
+        /// How many sources to try
+        #[structopt(short, long, default_value = "100")]
+        runs: u64,
+
+        /// Seed of the first source, each run after it uses the next seed
+        #[structopt(short, long, default_value = "0")]
+        seed: u64,
+
+        /// Longest token to generate
+        #[structopt(short, long, default_value = "8")]
+        max_length: usize,
+
+        /// Path to write the failing source to, or the last one if none fail
+        #[structopt(long, parse(from_os_str))]
+        source: Option<PathBuf>,
+
+        /// Path to write the expected tokens of that source to
+        #[structopt(long, parse(from_os_str))]
+        expected: Option<PathBuf>,
+    },
+
+    /// Scans sources and lists the transitions and accepting states of each
+    /// table that were never used
+    Coverage {
+        /// Path to the scanning definition file
+        #[structopt(parse(from_os_str))]
+        definition: PathBuf,
+
+        /// Paths to the sources to scan
+        #[structopt(parse(from_os_str), required = true)]
+        sources: Vec<PathBuf>,
+    },
+
+    /// Runs the driver on each definition and source in a directory and
+    /// compares the tokens with the expected ones
+    Test {
+        /// Directory with .u files, and sources and expected tokens next to them
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+
+        /// Overwrite the expected tokens of failing cases with the actual ones
+        #[structopt(long)]
+        bless: bool,
+    },
+
+    /// Reads texts from stdin and shows how each transition table matches them
+    Repl {
+        /// Path to the scanning definition file
+        #[structopt(parse(from_os_str))]
+        definition: PathBuf,
+    },
+
+    /// Lists the chars of a source that are not in the alphabet, with how
+    /// often and where they first appear
+    AlphabetCheck {
+        /// Path to the scanning definition file
+        #[structopt(parse(from_os_str))]
+        definition: PathBuf,
+
+        /// Path to the source to check
+        #[structopt(parse(from_os_str))]
+        source: PathBuf,
+
+        /// Replace every char not in the alphabet with this one, plain or xHH
+        #[structopt(short, long)]
+        map_to: Option<String>,
+
+        /// Path to write the mapped source to, stdout if not given
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Reports transition tables that overlap or can never produce a token
+    Check {
+        /// Path to the scanning definition file
+        #[structopt(parse(from_os_str))]
+        definition: PathBuf,
+    },
 }
+
 fn main() {
+    if first_argument().is_some_and(|a| Path::new(&a).is_file() && !is_subcommand(&a)) {
+        let run = Run::from_args();
+        return scan(run.definition, run.source, run.output, run.trace);
+    }
     let args = Args::from_args();
+    if args.command.is_some() && (args.trace.trace || args.trace.trace_file.is_some()) {
+        Error::with_description(
+            "--trace and --trace-file only go with a plain run, not a subcommand",
+            ErrorKind::ArgumentConflict,
+        )
+        .exit();
+    }
 
     let temp_input_file_name = "some_tt.tt";
 
-    let scanner_def = Scanner::from_file(args.definition).unwrap_or_else(|_| std::process::exit(1));
+    match args.command {
+        Some(Command::Keywords {
+            words,
+            alphabet,
+            output,
+        }) => keywords(words, &alphabet, output),
+        Some(Command::Build {
+            expression,
+            alphabet,
+            output,
+        }) => build(&expression, &alphabet, output),
+        Some(Command::DiffDfa {
+            first,
+            second,
+            alphabet,
+        }) => diff_dfa(first, second, &alphabet),
+        Some(Command::DiffScanner { old, new, corpus }) => diff_scanner(old, new, corpus),
+        Some(Command::Examples {
+            table,
+            alphabet,
+            n,
+            covering,
+        }) => examples(table, &alphabet, n, covering),
+        Some(Command::Sample {
+            table,
+            alphabet,
+            length,
+            n,
+            seed,
+        }) => sample(table, &alphabet, length, n, seed),
+        Some(Command::Fuzz {
+            definition,
+            n,
+            runs,
+            seed,
+            max_length,
+            source,
+            expected,
+        }) => fuzz(definition, n, runs, seed, max_length, source, expected),
+        Some(Command::Coverage {
+            definition,
+            sources,
+        }) => coverage(definition, sources),
+        Some(Command::Test { dir, bless }) => test(dir, bless),
+        Some(Command::Repl { definition }) => {
+            let scanner = load_uncompressed(definition);
+            let stdin = std::io::stdin();
+            repl::run(&scanner, stdin.lock(), std::io::stdout())
+                .unwrap_or_else(|_| std::process::exit(4));
+        }
+        Some(Command::AlphabetCheck {
+            definition,
+            source,
+            map_to,
+            output,
+        }) => alphabet_check(definition, source, map_to, output),
+        Some(Command::Check { definition }) => check(definition),
+        None => match (args.definition, args.source, args.output) {
+            (Some(definition), Some(source), Some(output)) => {
+                scan(definition, source, output, args.trace)
+            }
+            _ => Error::with_description(
+                "<definition>, <source> and <output> are required without a subcommand",
+                ErrorKind::MissingRequiredArgument,
+            )
+            .exit(),
+        },
+    }
+}
+
+/// The first argument that isn't a flag or the value of one
+fn first_argument() -> Option<OsString> {
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--trace-file") => {
+                args.next();
+            }
+            Some(flag) if flag.starts_with('-') => {}
+            _ => return Some(arg),
+        }
+    }
+    None
+}
+
+/// Is the argument exactly the name of a subcommand
+fn is_subcommand(arg: &OsStr) -> bool {
+    match Command::clap().get_matches_from_safe(vec![OsStr::new("LUTHER"), arg]) {
+        Ok(_) => true,
+        Err(e) => !matches!(
+            e.kind,
+            ErrorKind::UnrecognizedSubcommand | ErrorKind::InvalidSubcommand
+        ),
+    }
+}
+
+fn scan(definition: PathBuf, source: PathBuf, output: PathBuf, trace: Trace) {
+    let scanner_def = load_scanner(definition);
+
+    let trace: Option<Box<dyn Write>> = match (trace.trace_file, trace.trace) {
+        (Some(path), _) => Some(Box::new(BufWriter::new(
+            File::create(path).unwrap_or_else(|_| std::process::exit(4)),
+        ))),
+        (None, true) => Some(Box::new(std::io::stderr())),
+        (None, false) => None,
+    };
+
+    Driver::run(scanner_def, source, output, trace);
+}
+
+/// The compressed scanner, for commands that scan text
+fn load_scanner(definition: PathBuf) -> Scanner {
+    let scanner = loaded(Scanner::load(definition));
+    if let Some(c) = scanner.compression() {
+        eprintln!(
+            "compression: {} columns into {}, saved {} of {} table bytes",
+            c.columns,
+            c.classes,
+            c.bytes_before - c.bytes_after,
+            c.bytes_before
+        );
+    }
+    scanner
+}
+
+/// The scanner with the alphabet line's columns, for analyses that compare
+/// tables column by column and commands that show columns or count them
+fn load_uncompressed(definition: PathBuf) -> Scanner {
+    loaded(Scanner::from_file(definition))
+}
+
+/// Prints the scanner's warnings, or the error it failed with and exits
+fn loaded(scanner: Result<Scanner, Box<dyn std::error::Error>>) -> Scanner {
+    let scanner = scanner.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
+    for warning in scanner.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    scanner
+}
+
+fn load_alphabet(alphabet: &str) -> (Alphabet, char) {
+    Scanner::alphabet_build(alphabet).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    })
+}
+
+fn keywords(words: PathBuf, alphabet: &str, output: Option<PathBuf>) {
+    let (alpha, _) = load_alphabet(alphabet);
+    let words = std::fs::read_to_string(words).unwrap_or_else(|_| std::process::exit(6));
+    // Only the first field is used, so a %keywords table works as well
+    let words: Vec<String> = words
+        .lines()
+        .filter_map(|l| l.split_whitespace().next())
+        .map(alphabet_translator::hex_a_string_to_string)
+        .collect();
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+
+    let dfa = DFA::from_keywords(&words, &alpha).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
+
+    write_dfa(&dfa, output);
+}
+
+fn build(expression: &str, alphabet: &str, output: Option<PathBuf>) {
+    let (alpha, _) = load_alphabet(alphabet);
+    let dfa = Expr::load(expression, alpha.columns()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2)
+    });
+
+    write_dfa(&dfa, output);
+}
+
+fn write_dfa(dfa: &DFA, output: Option<PathBuf>) {
+    match output {
+        Some(path) => dfa.to_file(path).unwrap_or_else(|_| std::process::exit(4)),
+        None => print!("{}", dfa),
+    }
+}
+
+fn load_dfa(path: &PathBuf) -> DFA {
+    DFA::from_file(path).unwrap_or_else(|_| std::process::exit(2))
+}
+
+fn diff_dfa(first: PathBuf, second: PathBuf, alphabet: &str) {
+    let (alpha, newline) = load_alphabet(alphabet);
+    let scanner = Scanner::new(newline, alpha, Vec::new());
+    let (a, b) = (load_dfa(&first), load_dfa(&second));
+
+    let word = match a.equivalent(&b) {
+        Ok(()) => {
+            println!(
+                "{} and {} accept the same texts",
+                first.display(),
+                second.display()
+            );
+            return;
+        }
+        Err(word) => word,
+    };
+    let (only, other) = if a.accepts(&word) {
+        (&first, &second)
+    } else {
+        (&second, &first)
+    };
+    println!(
+        "{} accepts \"{}\" but {} does not",
+        only.display(),
+        scanner.word_to_string(&word),
+        other.display()
+    );
+    if a.is_subset_of(&b).is_ok() {
+        println!(
+            "every text {} accepts, {} accepts too",
+            first.display(),
+            second.display()
+        );
+    } else if b.is_subset_of(&a).is_ok() {
+        println!(
+            "every text {} accepts, {} accepts too",
+            second.display(),
+            first.display()
+        );
+    }
+    std::process::exit(1);
+}
+
+fn examples(table: PathBuf, alphabet: &str, n: usize, covering: bool) {
+    let (alpha, _) = load_alphabet(alphabet);
+    let dfa = load_dfa(&table);
+
+    if covering {
+        for (text, accepted) in dfa.covering_strings(&alpha) {
+            let verdict = if accepted { "accepted" } else { "rejected" };
+            println!(
+                "\"{}\" {}",
+                alphabet_translator::readable_hex_a_string(&text),
+                verdict
+            );
+        }
+        return;
+    }
+
+    for text in dfa.strings(&alpha).take(n) {
+        println!("\"{}\"", alphabet_translator::readable_hex_a_string(&text));
+    }
+}
+
+fn sample(table: PathBuf, alphabet: &str, length: usize, n: usize, seed: u64) {
+    let (alpha, newline) = load_alphabet(alphabet);
+    let scanner = Scanner::new(newline, alpha, Vec::new());
+    let dfa = load_dfa(&table);
+    let mut random = Random::new(seed);
+
+    println!(
+        "{} accepts {} texts of length {}",
+        table.display(),
+        dfa.count_of_length(length),
+        length
+    );
+    for _ in 0..n {
+        match dfa.sample(length, &mut random) {
+            Some(word) => println!("\"{}\"", scanner.word_to_string(&word)),
+            None => break,
+        }
+    }
+}
+
+fn fuzz(
+    definition: PathBuf,
+    n: usize,
+    runs: u64,
+    seed: u64,
+    max_length: usize,
+    source: Option<PathBuf>,
+    expected: Option<PathBuf>,
+) {
+    let scanner = load_scanner(definition);
+    let fuzzer = Fuzzer::new(&scanner, max_length);
+    let mut sample = None;
+    let mut failed = false;
+
+    for seed in seed..seed + runs {
+        let generated = fuzzer.generate(n, &mut Random::new(seed));
+        let tokens = Driver::tokenize(&scanner, &generated.source).unwrap_or_else(|e| e.tokens);
+        failed = tokens != generated.tokens;
+        if failed {
+            let index = tokens
+                .iter()
+                .zip(generated.tokens.iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+            println!("seed {}: token {} differs", seed, index + 1);
+            match generated.tokens.get(index) {
+                Some(token) => println!("expected: {}", token),
+                None => println!("expected: nothing"),
+            }
+            match tokens.get(index) {
+                Some(token) => println!("got: {}", token),
+                None => println!("got: nothing"),
+            }
+        }
+        sample = Some(generated);
+        if failed {
+            break;
+        }
+    }
+
+    if let Some(sample) = sample.as_ref() {
+        if let Some(path) = source {
+            std::fs::write(path, &sample.source).unwrap_or_else(|_| std::process::exit(4));
+        }
+        if let Some(path) = expected {
+            let lines: String = sample.tokens.iter().map(|t| format!("{}\n", t)).collect();
+            std::fs::write(path, lines).unwrap_or_else(|_| std::process::exit(4));
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    println!("{} sources tokenized as expected", runs);
+}
+
+fn coverage(definition: PathBuf, sources: Vec<PathBuf>) {
+    let scanner = load_uncompressed(definition);
+    let mut coverage = Coverage::new(&scanner);
+    for path in sources {
+        let source = std::fs::read_to_string(&path).unwrap_or_else(|_| std::process::exit(6));
+        if let Err(e) = Driver::tokenize_with_coverage(&scanner, &source, &mut coverage) {
+            eprint!(
+                "{}",
+                e.render(&path.display().to_string(), &source, scanner.newline)
+            );
+        }
+    }
+
+    for (index, t) in scanner.get_trans().iter().enumerate() {
+        let uncovered = coverage.uncovered_transitions(&scanner, index);
+        println!(
+            "table: {} took {} of {} transitions",
+            t.id,
+            coverage.visits[index].len(),
+            coverage.visits[index].len() + uncovered.len()
+        );
+        for (state, column) in uncovered {
+            println!(
+                "uncovered: {} row {} on \"{}\"",
+                t.id,
+                state,
+                scanner.word_to_string(&[column])
+            );
+        }
+        for state in coverage.uncovered_accepting(&scanner, index) {
+            println!("uncovered: {} accepting row {} never reached", t.id, state);
+        }
+    }
+}
+
+fn alphabet_check(
+    definition: PathBuf,
+    source: PathBuf,
+    map_to: Option<String>,
+    output: Option<PathBuf>,
+) {
+    let scanner = load_scanner(definition);
+    let source = std::fs::read_to_string(source).unwrap_or_else(|_| std::process::exit(6));
+
+    let unknown = scanner.unknown_chars(&source);
+    for u in unknown.iter() {
+        eprintln!(
+            "not in alphabet: \"{}\" appears {} times, first at {}:{}",
+            alphabet_translator::readable_hex_a_string(&u.letter.to_string()),
+            u.count,
+            u.line,
+            u.column
+        );
+    }
+
+    let map_to = match map_to {
+        Some(map_to) => map_to,
+        None if unknown.is_empty() => return,
+        None => std::process::exit(42),
+    };
+    let decoded = alphabet_translator::hex_a_string_to_string(&map_to);
+    let mut letters = decoded.chars();
+    let catch_all = match (letters.next(), letters.next()) {
+        (Some(c), None) => c,
+        _ => {
+            eprintln!("--map-to takes a single char, not \"{}\"", map_to);
+            std::process::exit(1)
+        }
+    };
+    let mapped = scanner
+        .map_unknown_chars(&source, catch_all)
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        });
+    match output {
+        Some(path) => std::fs::write(path, mapped).unwrap_or_else(|_| std::process::exit(4)),
+        None => print!("{}", mapped),
+    }
+}
+
+fn test(dir: PathBuf, bless: bool) {
+    let cases = Case::discover(&dir).unwrap_or_else(|e| {
+        eprintln!("{}: {}", dir.display(), e);
+        std::process::exit(1)
+    });
+    let mut failed = 0;
+
+    for case in cases.iter() {
+        let result = case.run().and_then(|actual| Ok((case.expected()?, actual)));
+        let (expected, actual) = match result {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                println!("error: {}: {}", case.definition.display(), e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        let changes = golden::diff(&expected, &actual);
+        if changes.is_empty() && case.expected.exists() {
+            println!("ok: {}", case.definition.display());
+            continue;
+        }
+        if bless {
+            case.bless(&actual)
+                .unwrap_or_else(|_| std::process::exit(4));
+            println!("blessed: {}", case.expected.display());
+            continue;
+        }
+
+        failed += 1;
+        println!("FAIL: {}", case.definition.display());
+        if !case.expected.exists() {
+            println!("  {} does not exist", case.expected.display());
+        }
+        print_changes(changes);
+    }
+
+    println!("{} passed, {} failed", cases.len() - failed, failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn print_changes(changes: Vec<Change>) {
+    for change in changes {
+        match change {
+            Change::Missing(index, token) => println!("  - {}: {}", index + 1, token),
+            Change::Extra(index, token) => println!("  + {}: {}", index + 1, token),
+        }
+    }
+}
+
+fn diff_scanner(old_path: PathBuf, new_path: PathBuf, corpus: Vec<PathBuf>) {
+    let (old, new) = (load_uncompressed(old_path), load_uncompressed(new_path));
+    let (old_trans, new_trans) = (old.get_trans(), new.get_trans());
+    let mut differs = false;
+
+    if old.policy != new.policy {
+        println!("policy: {} became {}", old.policy, new.policy);
+        differs = true;
+    }
+
+    if old.get_alpha() != new.get_alpha() {
+        println!("alphabet: the alphabets differ, only the corpus is compared");
+        differs = true;
+    } else {
+        for t in old_trans.iter() {
+            let n = match new_trans.iter().find(|n| n.id == t.id) {
+                Some(n) => n,
+                None => {
+                    println!("removed: {}", t.id);
+                    differs = true;
+                    continue;
+                }
+            };
+            if let Err(word) = t.tt.equivalent(&n.tt) {
+                let (has, lacks) = if t.tt.accepts(&word) {
+                    ("old", "new")
+                } else {
+                    ("new", "old")
+                };
+                println!(
+                    "changed: {} {} matches \"{}\" but {} does not",
+                    t.id,
+                    has,
+                    old.word_to_string(&word),
+                    lacks
+                );
+                differs = true;
+            }
+            if t.priority != n.priority {
+                println!("priority: {} {} became {}", t.id, t.priority, n.priority);
+                differs = true;
+            }
+        }
+        for n in new_trans.iter() {
+            if !old_trans.iter().any(|t| t.id == n.id) {
+                println!("added: {}", n.id);
+                differs = true;
+            }
+        }
+
+        let token = |trans: &[TransitionTable], first: Option<(usize, usize)>| match first {
+            Some((t, length)) => format!("{} {}", trans[t].id, length),
+            None => "nothing".to_string(),
+        };
+        for difference in analysis::differences(&old, &new) {
+            println!(
+                "differs: \"{}\" old {} new {}",
+                old.word_to_string(&difference.example),
+                token(old_trans, difference.old),
+                token(new_trans, difference.new)
+            );
+            differs = true;
+        }
+    }
+
+    for path in corpus {
+        let source = std::fs::read_to_string(&path).unwrap_or_else(|_| std::process::exit(6));
+        let changes = golden::diff(
+            &Driver::tokenize(&old, &source).unwrap_or_else(|e| e.tokens),
+            &Driver::tokenize(&new, &source).unwrap_or_else(|e| e.tokens),
+        );
+        if !changes.is_empty() {
+            println!("corpus: {}", path.display());
+            print_changes(changes);
+            differs = true;
+        }
+    }
+
+    if differs {
+        std::process::exit(1);
+    }
+    println!("the scanners tokenize the same way");
+}
+
+fn check(definition: PathBuf) {
+    let mut scanner = load_uncompressed(definition);
+    let trans = scanner.get_trans();
+
+    for t in trans.iter() {
+        match t.tt.max_length() {
+            Some(length) => println!("table: {} longest match is {}", t.id, length),
+            None => println!("table: {} longest match is unbounded", t.id),
+        }
+        if t.tt.accepts_empty() {
+            println!("table: {} matches the empty string", t.id);
+        }
+    }
+
+    for overlap in analysis::overlaps(&scanner) {
+        println!(
+            "overlap: {} and {} both match {}, {} wins",
+            trans[overlap.first].id,
+            trans[overlap.second].id,
+            scanner.word_to_string(&overlap.example),
+            trans[overlap.winner].id
+        );
+    }
+
+    let shadowed = analysis::shadowed(&scanner);
+    for &index in shadowed.iter() {
+        println!(
+            "shadowed: {} never produces a token, other tables always win under {}",
+            trans[index].id, scanner.policy
+        );
+    }
+
+    let compression = scanner.compress();
+    println!(
+        "compression: {} columns into {}, tables take {} bytes instead of {}",
+        compression.columns, compression.classes, compression.bytes_after, compression.bytes_before
+    );
 
-    Driver::run(scanner_def, args.source, args.output);
+    if !shadowed.is_empty() {
+        std::process::exit(1);
+    }
 }
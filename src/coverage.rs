@@ -0,0 +1,99 @@
+use crate::regex::Visits;
+use crate::scanner::Scanner;
+
+/// The transitions each transition table took while scanning, added up over
+/// all the sources scanned with it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    pub visits: Vec<Visits>,
+}
+
+impl Coverage {
+    pub fn new(scanner: &Scanner) -> Self {
+        Self {
+            visits: vec![Visits::new(); scanner.get_trans().len()],
+        }
+    }
+
+    /// Adds the visits of one scan of a table
+    pub fn add(&mut self, table: usize, visits: Visits) {
+        for (transition, count) in visits {
+            *self.visits[table].entry(transition).or_insert(0) += count;
+        }
+    }
+
+    /// The (state, column) transitions of a table that go somewhere but
+    /// were never taken
+    pub fn uncovered_transitions(&self, scanner: &Scanner, table: usize) -> Vec<(usize, usize)> {
+        let dfa = &scanner.get_trans()[table].tt;
+        (0..dfa.states())
+            .flat_map(|state| (0..dfa.columns()).map(move |column| (state, column)))
+            .filter(|&(state, column)| dfa.next(state, column).is_some())
+            .filter(|transition| !self.visits[table].contains_key(transition))
+            .collect()
+    }
+
+    /// The accepting states of a table no transition taken ever led to
+    pub fn uncovered_accepting(&self, scanner: &Scanner, table: usize) -> Vec<usize> {
+        let dfa = &scanner.get_trans()[table].tt;
+        let reached: Vec<usize> = self.visits[table]
+            .keys()
+            .filter_map(|&(state, column)| dfa.next(state, column))
+            .collect();
+        (0..dfa.states())
+            .filter(|&state| dfa.is_accepting(state) && !reached.contains(&state))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::driver::Driver;
+
+    #[test]
+    fn wiki_source() {
+        let scanner = Scanner::from_file("wiki/scan.u").unwrap();
+        let source = std::fs::read_to_string("wiki/source.txt").unwrap();
+        let mut coverage = Coverage::new(&scanner);
+        let tokens = Driver::tokenize_with_coverage(&scanner, &source, &mut coverage).unwrap();
+        assert_eq!(tokens, Driver::tokenize(&scanner, &source).unwrap());
+
+        // noto never starts on q or s in the source, and never follows with s
+        assert_eq!(
+            coverage.uncovered_transitions(&scanner, 0),
+            vec![(0, 5), (0, 7), (1, 7)]
+        );
+        assert_eq!(coverage.uncovered_accepting(&scanner, 0), vec![]);
+        assert!(coverage.visits[0][&(0, 4)] > 0);
+    }
+
+    #[test]
+    fn scans_add_up() {
+        let scanner = Scanner::from_file("wiki/scan.u").unwrap();
+        let mut once = Coverage::new(&scanner);
+        Driver::tokenize_with_coverage(&scanner, "pq", &mut once).unwrap();
+        let mut twice = Coverage::new(&scanner);
+        Driver::tokenize_with_coverage(&scanner, "pq", &mut twice).unwrap();
+        Driver::tokenize_with_coverage(&scanner, "pq", &mut twice).unwrap();
+        for (a, b) in once.visits.iter().zip(twice.visits.iter()) {
+            assert_eq!(a.keys().collect::<Vec<_>>(), b.keys().collect::<Vec<_>>());
+            assert!(a.iter().all(|(t, &count)| b[t] == 2 * count));
+        }
+
+        // noto takes p then q, and nothing else
+        let uncovered = once.uncovered_transitions(&scanner, 0);
+        assert_eq!(uncovered.len(), 8 - 2);
+        assert!(!uncovered.contains(&(0, 4)) && !uncovered.contains(&(1, 5)));
+    }
+
+    #[test]
+    fn nothing_scanned() {
+        let scanner = Scanner::from_file("wiki/scan.u").unwrap();
+        let mut coverage = Coverage::new(&scanner);
+        Driver::tokenize_with_coverage(&scanner, "", &mut coverage).unwrap();
+        assert!(coverage.visits.iter().all(|v| v.is_empty()));
+        assert_eq!(coverage.uncovered_accepting(&scanner, 0), vec![1]);
+        assert_eq!(coverage.uncovered_transitions(&scanner, 0).len(), 8);
+    }
+}
@@ -1,15 +1,62 @@
+use crate::alphabet::Alphabet;
+use crate::nfa::NFA;
+use crate::product::Product;
+use crate::random::Random;
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::TryFrom;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 
+/// Stands for `E` in the flat table
+const DEAD: u32 = u32::MAX;
+
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct DFA {
-    rows: Vec<Row>,
+    /// Row r is table[r * columns..(r + 1) * columns], all rows as wide as
+    /// the widest one in the file
+    table: Vec<u32>,
+    accepting: Vec<bool>,
+    columns: usize,
 }
 
 impl DFA {
+    /// Rows are numbered in order, whatever their ids say
     fn new(rows: Vec<Row>) -> Self {
-        Self { rows }
+        let columns = rows
+            .iter()
+            .map(|r| r.transitions().len())
+            .max()
+            .unwrap_or(0);
+        let mut table = vec![DEAD; rows.len() * columns];
+        for (r, row) in rows.iter().enumerate() {
+            for c in 0..columns {
+                let next = row.transitions().get(c).copied().unwrap_or(row.rest);
+                if let Some(next) = next {
+                    table[r * columns + c] = u32::try_from(next)
+                        .ok()
+                        .filter(|&next| next != DEAD)
+                        .expect("row past the end of the flat table");
+                }
+            }
+        }
+
+        Self {
+            table,
+            accepting: rows.iter().map(Row::is_accepting).collect(),
+            columns,
+        }
+    }
+
+    /// The rows as they are written to a file
+    fn rows(&self) -> Vec<Row> {
+        (0..self.states())
+            .map(|r| {
+                let transitions = (0..self.columns).map(|c| self.transition(r, c)).collect();
+                Row::new(self.is_accepting(r), r, transitions)
+            })
+            .collect()
     }
 
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
@@ -18,10 +65,16 @@ impl DFA {
 
         let all_rows = reader.lines().flatten();
         let mut rows: Vec<Row> = Vec::new();
-        for (_, row) in all_rows.enumerate() {
+        for (line, row) in all_rows.enumerate() {
+            if row.trim().is_empty() {
+                continue;
+            }
             match Row::from_str_custom(&row) {
+                Ok(row) if !row.fits() => {
+                    return Err(format!("row {} goes to a row past {}", row.id, DEAD - 1).into())
+                }
                 Ok(row) => rows.push(row),
-                _ => break,
+                Err(()) => return Err(format!("line {} is not a row: {}", line + 1, row).into()),
             }
             // rows.push(Row::from_str_custom(&row).unwrap());
         }
@@ -30,12 +83,731 @@ impl DFA {
         // DFA::new(Vec::new())
     }
 
+    /// Builds a DFA from the accepting flag and transitions of each row,
+    /// numbering the rows in order.
+    pub(crate) fn from_table(accepting: Vec<bool>, transitions: Vec<Vec<Option<usize>>>) -> Self {
+        let rows = accepting
+            .into_iter()
+            .zip(transitions)
+            .enumerate()
+            .map(|(id, (is_accepting, transitions))| Row::new(is_accepting, id, transitions))
+            .collect();
+
+        DFA::new(rows)
+    }
+
+    /// Builds the minimal DFA accepting exactly the given keywords, by
+    /// building a trie and merging equivalent states.
+    pub fn from_keywords(
+        keywords: &[&str],
+        alpha: &Alphabet,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let columns = alpha.columns();
+        let mut accepting = vec![false];
+        let mut transitions = vec![vec![None; columns]];
+
+        for keyword in keywords {
+            let mut state = 0;
+            for letter in keyword.chars() {
+                let column = *alpha
+                    .get(&letter)
+                    .ok_or_else(|| format!("{:?} is not in the alphabet", letter))?;
+                state = match transitions[state][column] {
+                    Some(next) => next,
+                    None => {
+                        accepting.push(false);
+                        transitions.push(vec![None; columns]);
+                        transitions[state][column] = Some(accepting.len() - 1);
+                        accepting.len() - 1
+                    }
+                };
+            }
+            accepting[state] = true;
+        }
+
+        Ok(DFA::from_table(accepting, transitions).minimize())
+    }
+
+    pub fn to_file(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    /// Columns past the end of the rows are `E`
     pub fn transition(&self, row: usize, letter: usize) -> Option<usize> {
-        self.rows[row].transitions()[letter]
+        if letter >= self.columns {
+            return None;
+        }
+        match self.table[row * self.columns + letter] {
+            DEAD => None,
+            next => Some(next as usize),
+        }
     }
 
     pub fn is_accepting(&self, row: usize) -> bool {
-        self.rows[row].is_accepting()
+        self.accepting[row]
+    }
+
+    /// The table with only the given columns, in that order
+    pub fn select_columns(&self, columns: &[usize]) -> DFA {
+        let accepting = (0..self.states()).map(|r| self.is_accepting(r)).collect();
+        let transitions = (0..self.states())
+            .map(|r| columns.iter().map(|&c| self.next(r, c)).collect())
+            .collect();
+        DFA::from_table(accepting, transitions)
+    }
+
+    /// How much memory the transitions take
+    pub fn table_bytes(&self) -> usize {
+        std::mem::size_of_val(self.table.as_slice())
+    }
+
+    /// The number of rows (states) in the table
+    pub fn states(&self) -> usize {
+        self.accepting.len()
+    }
+
+    /// The number of columns (alphabet symbols) in the table
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Like `transition`, but transitions to rows that don't exist are `E`
+    pub(crate) fn next(&self, row: usize, letter: usize) -> Option<usize> {
+        self.transition(row, letter)
+            .filter(|&next| next < self.states())
+    }
+
+    /// Does row 0 accept, so the DFA matches the empty string
+    pub fn accepts_empty(&self) -> bool {
+        self.states() > 0 && self.is_accepting(0)
+    }
+
+    /// Is there no text at all that the DFA accepts
+    pub fn is_empty(&self) -> bool {
+        !self
+            .reachable()
+            .iter()
+            .enumerate()
+            .any(|(row, &reached)| reached && self.is_accepting(row))
+    }
+
+    /// Does the DFA accept only finitely many texts
+    pub fn is_finite(&self) -> bool {
+        self.longest_paths().is_some()
+    }
+
+    /// The length of the longest text the DFA accepts. None if the DFA
+    /// accepts nothing or texts of any length.
+    pub fn max_length(&self) -> Option<usize> {
+        self.longest_paths()?.first().copied().flatten()
+    }
+
+    /// Checks that both DFAs accept the same texts over the same columns.
+    /// Otherwise returns the shortest text, as columns, only one of them accepts.
+    pub fn equivalent(&self, other: &DFA) -> Result<(), Vec<usize>> {
+        match Product::new(vec![self, other]).shortest_word(|a| a[0] != a[1]) {
+            Some(word) => Err(word),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that every text this DFA accepts is accepted by the other too.
+    /// Otherwise returns the shortest text, as columns, only this one accepts.
+    pub fn is_subset_of(&self, other: &DFA) -> Result<(), Vec<usize>> {
+        match Product::new(vec![self, other]).shortest_word(|a| a[0] && !a[1]) {
+            Some(word) => Err(word),
+            None => Ok(()),
+        }
+    }
+
+    /// Accepts the texts either DFA accepts
+    pub fn union(&self, other: &DFA) -> DFA {
+        Product::new(vec![self, other])
+            .build(|a| a[0] || a[1])
+            .minimize()
+    }
+
+    /// Accepts the texts both DFAs accept
+    pub fn intersection(&self, other: &DFA) -> DFA {
+        Product::new(vec![self, other])
+            .build(|a| a[0] && a[1])
+            .minimize()
+    }
+
+    /// Accepts the texts this DFA accepts and the other does not
+    pub fn difference(&self, other: &DFA) -> DFA {
+        Product::new(vec![self, other])
+            .build(|a| a[0] && !a[1])
+            .minimize()
+    }
+
+    /// Accepts the texts over the given number of columns this DFA does not
+    pub fn complement(&self, columns: usize) -> DFA {
+        let columns = columns.max(self.columns());
+        let dead = self.states();
+        let mut accepting: Vec<bool> = (0..dead).map(|row| !self.is_accepting(row)).collect();
+        let mut transitions: Vec<Vec<Option<usize>>> = (0..dead)
+            .map(|row| {
+                (0..columns)
+                    .map(|letter| Some(self.next(row, letter).unwrap_or(dead)))
+                    .collect()
+            })
+            .collect();
+        // E becomes a real row that accepts everything from then on
+        accepting.push(true);
+        transitions.push(vec![Some(dead); columns]);
+
+        DFA::from_table(accepting, transitions).minimize()
+    }
+
+    /// Accepts a text of this DFA followed by a text of the other
+    pub fn concat(&self, other: &DFA) -> DFA {
+        NFA::from_dfa(self)
+            .concat(&NFA::from_dfa(other))
+            .to_dfa()
+            .minimize()
+    }
+
+    /// Accepts zero or more texts of this DFA in a row
+    pub fn star(&self) -> DFA {
+        NFA::from_dfa(self).star().to_dfa().minimize()
+    }
+
+    /// Accepts one or more texts of this DFA in a row
+    pub fn plus(&self) -> DFA {
+        NFA::from_dfa(self).plus().to_dfa().minimize()
+    }
+
+    /// Accepts the empty text, or a text of this DFA
+    pub fn optional(&self) -> DFA {
+        NFA::from_dfa(self).optional().to_dfa().minimize()
+    }
+
+    /// Accepts the texts of this DFA spelled backwards
+    pub fn reverse(&self) -> DFA {
+        NFA::from_dfa(self).reverse().to_dfa().minimize()
+    }
+
+    /// Does the DFA accept the given text, as columns
+    pub fn accepts(&self, word: &[usize]) -> bool {
+        let mut state = if self.states() > 0 { Some(0) } else { None };
+        for &letter in word {
+            state = state.and_then(|s| self.next(s, letter));
+        }
+        state.is_some_and(|s| self.is_accepting(s))
+    }
+
+    /// The length of the longest non-empty prefix of the word the DFA
+    /// accepts, or of the shortest one
+    pub fn prefix_length(&self, word: &[usize], shortest: bool) -> Option<usize> {
+        let mut state = if self.states() > 0 { Some(0) } else { None };
+        let mut found = None;
+        for (length, &letter) in word.iter().enumerate() {
+            state = state.and_then(|s| self.next(s, letter));
+            match state {
+                Some(s) if self.is_accepting(s) => {
+                    found = Some(length + 1);
+                    if shortest {
+                        break;
+                    }
+                }
+                Some(_) => {}
+                None => break,
+            }
+        }
+        found
+    }
+
+    /// The texts the DFA accepts, as columns, shortest first and in column
+    /// order among texts of the same length. Infinite if the language is.
+    pub fn words(&self) -> Words<'_> {
+        Words {
+            dfa: self,
+            counts: vec![self.accepting_counts()],
+            // A finite language has no text as long as the DFA has rows
+            limit: if self.is_finite() {
+                self.states()
+            } else {
+                usize::MAX
+            },
+            length: 0,
+            path: None,
+        }
+    }
+
+    /// Like `words`, but each column is turned back into a char of the alphabet
+    pub fn strings<'a>(&'a self, alpha: &Alphabet) -> impl Iterator<Item = String> + 'a {
+        let symbols = self.symbols(alpha);
+        self.words()
+            .map(move |word| word.iter().filter_map(|&c| symbols[c]).collect())
+    }
+
+    /// The first char of the alphabet in each column, and a char that isn't
+    /// listed for the other column
+    fn symbols(&self, alpha: &Alphabet) -> Vec<Option<char>> {
+        let mut symbols = vec![None; self.columns()];
+        for (&letter, &column) in alpha.iter().rev() {
+            if column < symbols.len() {
+                symbols[column] = Some(letter);
+            }
+        }
+        if let Some(other) = alpha.other().filter(|&other| other < symbols.len()) {
+            symbols[other] = alpha.other_letter();
+        }
+        symbols
+    }
+
+    /// A few texts, as columns, that between them take every transition out
+    /// of a reachable row and reach every reachable accepting row, each with
+    /// whether the DFA accepts it. Texts carry on to the nearest accepting
+    /// row when they can, so most of them should be accepted.
+    pub fn covering_words(&self) -> Vec<(Vec<usize>, bool)> {
+        let access = self.access_words();
+        let mut covered = vec![vec![false; self.columns()]; self.states()];
+        let mut found = vec![];
+        if self.accepts_empty() {
+            found.push((vec![], true));
+        }
+
+        for row in 0..self.states() {
+            let prefix = match &access[row] {
+                Some(prefix) => prefix,
+                None => continue,
+            };
+            for letter in 0..self.columns() {
+                let next = match self.next(row, letter) {
+                    Some(next) if !covered[row][letter] => next,
+                    _ => continue,
+                };
+                let mut word = prefix.clone();
+                word.push(letter);
+                word.extend(self.accepting_word(next).unwrap_or_default());
+
+                let mut state = 0;
+                for &letter in word.iter() {
+                    covered[state][letter] = true;
+                    state = self.next(state, letter).unwrap();
+                }
+                let accepted = self.is_accepting(state);
+                found.push((word, accepted));
+            }
+        }
+
+        found
+    }
+
+    /// Like `covering_words`, but each column is turned back into a char of the alphabet
+    pub fn covering_strings(&self, alpha: &Alphabet) -> Vec<(String, bool)> {
+        let symbols = self.symbols(alpha);
+        self.covering_words()
+            .into_iter()
+            .map(|(word, accepted)| (word.iter().filter_map(|&c| symbols[c]).collect(), accepted))
+            .collect()
+    }
+
+    /// The shortest text leading from row 0 to each row, None if it can't be reached
+    fn access_words(&self) -> Vec<Option<Vec<usize>>> {
+        let mut access = vec![None; self.states()];
+        let mut queue = VecDeque::new();
+        if self.states() > 0 {
+            access[0] = Some(vec![]);
+            queue.push_back(0);
+        }
+        while let Some(row) = queue.pop_front() {
+            for letter in 0..self.columns() {
+                if let Some(next) = self.next(row, letter) {
+                    if access[next].is_none() {
+                        let mut word = access[row].clone().unwrap();
+                        word.push(letter);
+                        access[next] = Some(word);
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        access
+    }
+
+    /// The shortest text leading from a row to an accepting row
+    fn accepting_word(&self, from: usize) -> Option<Vec<usize>> {
+        let mut paths = vec![None; self.states()];
+        let mut queue = VecDeque::new();
+        paths[from] = Some(vec![]);
+        queue.push_back(from);
+        while let Some(row) = queue.pop_front() {
+            if self.is_accepting(row) {
+                return paths[row].take();
+            }
+            for letter in 0..self.columns() {
+                if let Some(next) = self.next(row, letter) {
+                    if paths[next].is_none() {
+                        let mut word: Vec<usize> = paths[row].clone().unwrap();
+                        word.push(letter);
+                        paths[next] = Some(word);
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// counts[k][row] is how many texts of length k are accepted starting
+    /// from row, for k up to length. Saturates at u128::MAX.
+    fn counts(&self, length: usize) -> Vec<Vec<u128>> {
+        let mut counts = vec![self.accepting_counts()];
+        for _ in 1..=length {
+            self.count_longer(&mut counts);
+        }
+        counts
+    }
+
+    /// counts[0] of `counts`, the rows that accept the empty text
+    fn accepting_counts(&self) -> Vec<u128> {
+        (0..self.states())
+            .map(|row| self.is_accepting(row) as u128)
+            .collect()
+    }
+
+    /// Adds the counts for texts one letter longer than the last ones
+    fn count_longer(&self, counts: &mut Vec<Vec<u128>>) {
+        let shorter = counts.last().unwrap();
+        let row_counts = (0..self.states())
+            .map(|row| {
+                (0..self.columns())
+                    .filter_map(|letter| self.next(row, letter))
+                    .fold(0u128, |sum, next| sum.saturating_add(shorter[next]))
+            })
+            .collect();
+        counts.push(row_counts);
+    }
+
+    /// How many texts of the given length the DFA accepts, u128::MAX if
+    /// there are at least that many
+    pub fn count_of_length(&self, length: usize) -> u128 {
+        if self.states() == 0 {
+            return 0;
+        }
+        self.counts(length)[length][0]
+    }
+
+    /// A text of the given length, as columns, picked uniformly at random
+    /// among the ones the DFA accepts. None if there aren't any. Once the
+    /// counts saturate the pick is no longer quite uniform.
+    pub fn sample(&self, length: usize, random: &mut Random) -> Option<Vec<usize>> {
+        let counts = self.counts(length);
+        if self.states() == 0 || counts[length][0] == 0 {
+            return None;
+        }
+
+        let mut row = 0;
+        let mut word = vec![];
+        for remaining in (0..length).rev() {
+            let mut pick = random.below(counts[remaining + 1][row]);
+            for letter in 0..self.columns() {
+                if let Some(next) = self.next(row, letter) {
+                    let count = counts[remaining][next];
+                    if pick < count {
+                        word.push(letter);
+                        row = next;
+                        break;
+                    }
+                    pick -= count;
+                }
+            }
+        }
+        Some(word)
+    }
+
+    /// Rows that can be reached from row 0
+    fn reachable(&self) -> Vec<bool> {
+        let mut reached = vec![false; self.states()];
+        let mut stack = vec![];
+        if self.states() > 0 {
+            reached[0] = true;
+            stack.push(0);
+        }
+        while let Some(row) = stack.pop() {
+            for letter in 0..self.columns() {
+                if let Some(next) = self.next(row, letter) {
+                    if !reached[next] {
+                        reached[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        reached
+    }
+
+    /// For each row, the length of the longest path from it to an accepting
+    /// row, or None if there is no such path. Returns None if a reachable row
+    /// that can still accept is on a cycle, since paths are then unbounded.
+    fn longest_paths(&self) -> Option<Vec<Option<usize>>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Visit {
+            New,
+            Open,
+            Done,
+        }
+
+        let reachable = self.reachable();
+        let mut longest: Vec<Option<usize>> = vec![None; self.states()];
+        let mut visits = vec![Visit::New; self.states()];
+        // Depth first, each entry is a row and the next column to try from it
+        for root in (0..self.states()).filter(|&r| reachable[r]) {
+            if visits[root] != Visit::New {
+                continue;
+            }
+            visits[root] = Visit::Open;
+            let mut stack = vec![(root, 0)];
+            while let Some((row, letter)) = stack.pop() {
+                if letter == self.columns() {
+                    visits[row] = Visit::Done;
+                    if self.is_accepting(row) {
+                        longest[row] = longest[row].max(Some(0));
+                    }
+                    // The row below on the stack is the one this row was reached from
+                    if let (Some(&(parent, _)), Some(length)) = (stack.last(), longest[row]) {
+                        longest[parent] = longest[parent].max(Some(length + 1));
+                    }
+                    continue;
+                }
+                stack.push((row, letter + 1));
+                if let Some(next) = self.next(row, letter) {
+                    match visits[next] {
+                        Visit::New => {
+                            visits[next] = Visit::Open;
+                            stack.push((next, 0));
+                        }
+                        Visit::Open => {
+                            // A cycle only matters if it can still lead to acceptance
+                            if self.can_accept_from(next) {
+                                return None;
+                            }
+                        }
+                        Visit::Done => {
+                            if let Some(length) = longest[next] {
+                                longest[row] = longest[row].max(Some(length + 1));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Some(longest)
+    }
+
+    /// Can an accepting row be reached from the given row
+    fn can_accept_from(&self, row: usize) -> bool {
+        let mut seen = vec![false; self.states()];
+        let mut stack = vec![row];
+        seen[row] = true;
+        while let Some(row) = stack.pop() {
+            if self.is_accepting(row) {
+                return true;
+            }
+            for letter in 0..self.columns() {
+                if let Some(next) = self.next(row, letter) {
+                    if !seen[next] {
+                        seen[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Returns the equivalent DFA with the fewest states. Unreachable rows
+    /// are dropped and rows that can never reach an accepting row become `E`.
+    pub fn minimize(&self) -> DFA {
+        if self.states() == 0 {
+            return self.clone();
+        }
+        let columns = self.columns();
+
+        // Only keep rows reachable from row 0, renumbered in BFS order
+        let mut order = vec![0];
+        let mut renumber = vec![None; self.states()];
+        renumber[0] = Some(0);
+        let mut i = 0;
+        while i < order.len() {
+            let row = order[i];
+            for letter in 0..columns {
+                if let Some(next) = self.next(row, letter) {
+                    if renumber[next].is_none() {
+                        renumber[next] = Some(order.len());
+                        order.push(next);
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        // Moore's partition refinement, with the E state as an extra row
+        let dead = order.len();
+        let successor = |row: usize, letter: usize| {
+            if row == dead {
+                dead
+            } else {
+                self.next(order[row], letter)
+                    .and_then(|n| renumber[n])
+                    .unwrap_or(dead)
+            }
+        };
+        let mut class: Vec<usize> = (0..=dead)
+            .map(|row| (row != dead && self.is_accepting(order[row])) as usize)
+            .collect();
+        let mut class_count = 0;
+        loop {
+            let mut signatures = BTreeMap::new();
+            let next_class: Vec<usize> = (0..=dead)
+                .map(|row| {
+                    let signature: Vec<usize> = std::iter::once(class[row])
+                        .chain((0..columns).map(|letter| class[successor(row, letter)]))
+                        .collect();
+                    let new_id = signatures.len();
+                    *signatures.entry(signature).or_insert(new_id)
+                })
+                .collect();
+            class = next_class;
+            if signatures.len() == class_count {
+                break;
+            }
+            class_count = signatures.len();
+        }
+
+        // Rebuild one row per class, starting from the class of row 0 and
+        // leaving out the class of the E state
+        let dead_class = class[dead];
+        if class[0] == dead_class {
+            return DFA::from_table(vec![false], vec![vec![None; columns]]);
+        }
+        let mut rows_of_class: Vec<Option<usize>> = vec![None; class_count];
+        rows_of_class[class[0]] = Some(0);
+        let mut representatives = vec![0];
+        let mut queue = VecDeque::from(vec![0]);
+        while let Some(row) = queue.pop_front() {
+            for letter in 0..columns {
+                let next = successor(row, letter);
+                if class[next] != dead_class && rows_of_class[class[next]].is_none() {
+                    rows_of_class[class[next]] = Some(representatives.len());
+                    representatives.push(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let accepting = representatives
+            .iter()
+            .map(|&row| self.is_accepting(order[row]))
+            .collect();
+        let transitions = representatives
+            .iter()
+            .map(|&row| {
+                (0..columns)
+                    .map(|letter| rows_of_class[class[successor(row, letter)]])
+                    .collect()
+            })
+            .collect();
+
+        DFA::from_table(accepting, transitions)
+    }
+}
+
+/// Iterator over the texts a DFA accepts, see `DFA::words`. The texts of
+/// each length are listed depth first, only stepping into rows that accept
+/// some text of the length that is left, so nothing is built that doesn't
+/// lead to a text.
+pub struct Words<'d> {
+    dfa: &'d DFA,
+    /// counts[k][row] is how many texts of length k are accepted from row
+    counts: Vec<Vec<u128>>,
+    /// No text is this long
+    limit: usize,
+    length: usize,
+    /// The (letter, row reached) steps of the last text listed, None
+    /// before the first text of the length
+    path: Option<Vec<(usize, usize)>>,
+}
+
+impl<'d> Words<'d> {
+    /// The first letter from `from` on that leads to a row accepting some
+    /// text of the remaining length, with that row
+    fn step(&self, row: usize, from: usize, remaining: usize) -> Option<(usize, usize)> {
+        (from..self.dfa.columns()).find_map(|letter| {
+            self.dfa
+                .next(row, letter)
+                .filter(|&next| self.counts[remaining][next] > 0)
+                .map(|next| (letter, next))
+        })
+    }
+
+    /// Extends the path to the first text of the length that starts with it
+    fn fill(&self, path: &mut Vec<(usize, usize)>) {
+        while path.len() < self.length {
+            let row = path.last().map_or(0, |&(_, row)| row);
+            let remaining = self.length - path.len() - 1;
+            path.push(self.step(row, 0, remaining).unwrap());
+        }
+    }
+
+    /// The text after the path among the ones of the same length
+    fn advance(&self, mut path: Vec<(usize, usize)>) -> Option<Vec<(usize, usize)>> {
+        while let Some((letter, _)) = path.pop() {
+            let row = path.last().map_or(0, |&(_, row)| row);
+            let remaining = self.length - path.len() - 1;
+            if let Some(step) = self.step(row, letter + 1, remaining) {
+                path.push(step);
+                self.fill(&mut path);
+                return Some(path);
+            }
+        }
+        None
+    }
+}
+
+impl<'d> Iterator for Words<'d> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.dfa.states() == 0 {
+            return None;
+        }
+        loop {
+            let path = match self.path.take() {
+                Some(path) => self.advance(path),
+                None if self.counts[self.length][0] > 0 => {
+                    let mut path = vec![];
+                    self.fill(&mut path);
+                    Some(path)
+                }
+                None => None,
+            };
+            if let Some(path) = path {
+                let word = path.iter().map(|&(letter, _)| letter).collect();
+                self.path = Some(path);
+                return Some(word);
+            }
+
+            // On to the next length
+            self.length += 1;
+            if self.length >= self.limit {
+                return None;
+            }
+            self.dfa.count_longer(&mut self.counts);
+        }
+    }
+}
+
+impl fmt::Display for DFA {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.rows() {
+            writeln!(f, "{}", row.to_shortest_string())?;
+        }
+        Ok(())
     }
 }
 
@@ -44,6 +816,25 @@ struct Row {
     is_accepting: bool,
     id: usize,
     transitions: Vec<Option<usize>>, // None here represents 'E'
+    rest: Option<usize>,             // where the columns past the end go
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            if self.is_accepting { '+' } else { '-' },
+            self.id
+        )?;
+        for transition in self.transitions.iter() {
+            match transition {
+                Some(n) => write!(f, " {}", n)?,
+                None => write!(f, " E")?,
+            }
+        }
+        Ok(())
+    }
 }
 
 // impl PartialEq for Row {
@@ -60,6 +851,7 @@ impl Row {
             is_accepting,
             id,
             transitions,
+            rest: None,
         }
     }
 
@@ -71,30 +863,139 @@ impl Row {
         self.is_accepting
     }
 
+    /// Do all the rows it goes to have a number below DEAD
+    fn fits(&self) -> bool {
+        self.transitions
+            .iter()
+            .chain(std::iter::once(&self.rest))
+            .flatten()
+            .all(|&next| next < DEAD as usize)
+    }
+
     //EX: - 0 E 1 E
     //EX: - 1 2 E E
+    // or sparse, with the columns not listed going to E or to the row after *
+    //EX: - 0 1:1 2:E
+    //EX: + 1 3-7:1 *:0
     pub fn from_str_custom(input: &str) -> Result<Self, ()> {
         let tokens: Vec<&str> = input.trim().split_whitespace().collect();
 
         match tokens.as_slice() {
+            [accept, row_id, entries @ ..] if entries.iter().any(|e| e.contains(':')) => {
+                Row::from_sparse(*accept == "+", row_id.parse().map_err(|_| ())?, entries)
+            }
             [accept, row_id, transitions @ ..] => {
                 let is_accept = *accept == "+";
-                let transitions: Vec<Option<usize>> = transitions
+                let transitions = transitions
                     .iter()
                     .map(|n| {
                         if *n == "E" {
-                            None
+                            Ok(None)
                         } else {
-                            Some(n.parse().unwrap())
+                            n.parse().map(Some).map_err(|_| ())
                         }
                     })
-                    .collect();
+                    .collect::<Result<Vec<Option<usize>>, ()>>()?;
 
-                Ok(Row::new(is_accept, row_id.parse().unwrap(), transitions))
+                Ok(Row::new(
+                    is_accept,
+                    row_id.parse().map_err(|_| ())?,
+                    transitions,
+                ))
             }
             _ => Err(()),
         }
     }
+
+    fn from_sparse(is_accepting: bool, id: usize, entries: &[&str]) -> Result<Self, ()> {
+        let target = |t: &str| match t {
+            "E" => Ok(None),
+            n => n.parse().map(Some).map_err(|_| ()),
+        };
+        let mut listed = vec![];
+        let mut rest = None;
+        let mut rest_listed = false;
+        for entry in entries {
+            let (columns, to) = entry.split_once(':').ok_or(())?;
+            if columns == "*" {
+                if rest_listed {
+                    return Err(());
+                }
+                rest_listed = true;
+                rest = target(to)?;
+                continue;
+            }
+            let (first, last) = columns.split_once('-').unwrap_or((columns, columns));
+            let first: usize = first.parse().map_err(|_| ())?;
+            let last: usize = last.parse().map_err(|_| ())?;
+            if first > last {
+                return Err(());
+            }
+            listed.push((first, last, target(to)?));
+        }
+        // Each column is listed at most once
+        let mut ranges: Vec<(usize, usize)> = listed.iter().map(|&(f, l, _)| (f, l)).collect();
+        ranges.sort_unstable();
+        if ranges.windows(2).any(|pair| pair[1].0 <= pair[0].1) {
+            return Err(());
+        }
+
+        let width = listed
+            .iter()
+            .map(|&(_, last, _)| last + 1)
+            .max()
+            .unwrap_or(0);
+        let mut transitions = vec![rest; width];
+        for (first, last, to) in listed {
+            transitions[first..=last].iter_mut().for_each(|t| *t = to);
+        }
+        Ok(Self {
+            is_accepting,
+            id,
+            transitions,
+            rest,
+        })
+    }
+
+    /// The row in sparse form, leaving out the columns that go to default.
+    /// The last column is always listed so the row keeps its width.
+    fn to_sparse_string(&self, default: Option<usize>) -> String {
+        let show = |t: Option<usize>| t.map_or("E".to_string(), |n| n.to_string());
+        let mut text = format!("{} {}", if self.is_accepting { '+' } else { '-' }, self.id);
+        let mut first = 0;
+        while first < self.transitions.len() {
+            let to = self.transitions[first];
+            let mut last = first;
+            while last + 1 < self.transitions.len() && self.transitions[last + 1] == to {
+                last += 1;
+            }
+            let end = last + 1 == self.transitions.len();
+            if to != default && first < last {
+                text.push_str(&format!(" {}-{}:{}", first, last, show(to)));
+            } else if to != default || end {
+                text.push_str(&format!(" {}:{}", last, show(to)));
+            }
+            first = last + 1;
+        }
+        if default.is_some() {
+            text.push_str(&format!(" *:{}", show(default)));
+        }
+        text
+    }
+
+    /// Whichever of the dense and sparse forms is shorter, dense on a tie
+    fn to_shortest_string(&self) -> String {
+        let mut defaults = vec![None];
+        for &t in self.transitions.iter() {
+            if t.is_some() && !defaults.contains(&t) {
+                defaults.push(t);
+            }
+        }
+        std::iter::once(self.to_string())
+            .chain(defaults.into_iter().map(|d| self.to_sparse_string(d)))
+            .min_by_key(String::len)
+            .unwrap()
+    }
 }
 
 #[cfg(test)]
@@ -112,7 +1013,7 @@ mod test {
     fn two_line_valid_file() {
         let in_file = DFA::from_file("tests/two_liner.tt").unwrap();
         assert_eq!(
-            in_file.rows,
+            in_file.rows(),
             vec![
                 Row::new(false, 0, vec![None, Some(1), None]),
                 Row::new(false, 1, vec![Some(2), None, None])
@@ -124,14 +1025,14 @@ mod test {
     #[test]
     fn empty_file_test() {
         let in_file = DFA::from_file("tests/empty_file.tt").unwrap();
-        assert_eq!(in_file.rows, vec![]);
+        assert_eq!(in_file.rows(), vec![]);
     }
 
     #[test]
     fn two_line_valid_file_with_extra_lines() {
         let in_file = DFA::from_file("tests/two_liner_extra_lines.tt").unwrap();
         assert_eq!(
-            in_file.rows,
+            in_file.rows(),
             vec![
                 Row::new(false, 0, vec![None, Some(1), None]),
                 Row::new(false, 1, vec![Some(2), None, None])
@@ -139,6 +1040,472 @@ mod test {
         );
     }
 
+    #[test]
+    fn short_rows_are_padded_with_e() {
+        let dfa = DFA::new(vec![
+            Row::new(false, 0, vec![Some(1)]),
+            Row::new(true, 1, vec![None, Some(1), Some(0)]),
+        ]);
+        assert_eq!((dfa.states(), dfa.columns()), (2, 3));
+        assert_eq!(dfa.transition(0, 0), Some(1));
+        assert_eq!(dfa.transition(0, 2), None);
+        assert_eq!(dfa.transition(1, 2), Some(0));
+        assert_eq!(dfa.transition(1, 3), None);
+        assert_eq!(dfa.to_string(), "- 0 1 E E\n+ 1 E 1 0\n");
+        assert_eq!(dfa.table_bytes(), 2 * 3 * 4);
+    }
+
+    #[test]
+    fn rows_past_the_table_are_refused() {
+        // 4294967295 would read as E in the flat table
+        assert!(DFA::from_file("tests/too_far.tt").is_err());
+        assert!(!Row::from_str_custom("- 0 0:4294967296").unwrap().fits());
+        assert!(Row::from_str_custom("- 0 0:4294967294").unwrap().fits());
+    }
+
+    #[test]
+    fn rows_that_dont_parse_are_refused() {
+        let error = DFA::from_file("tests/bad_row.tt").unwrap_err().to_string();
+        assert_eq!(error, "line 2 is not a row: - 1 0:2 0:E");
+        assert!(Row::from_str_custom("- 0 1 x").is_err());
+    }
+
+    // writing and minimizing
+    #[test]
+    fn display_round_trip() {
+        let dfa = DFA::from_file("wiki/twosmallwords.tt").unwrap();
+        let rows: Vec<Row> = dfa
+            .to_string()
+            .lines()
+            .map(|r| Row::from_str_custom(r).unwrap())
+            .collect();
+        assert_eq!(DFA::new(rows), dfa);
+        assert_eq!(dfa.to_string().lines().next(), Some("- 0 3-7:1"));
+    }
+
+    #[test]
+    fn sparse_round_trip() {
+        for file in &[
+            "wiki/noto.tt",
+            "wiki/nots.tt",
+            "wiki/endsq.tt",
+            "wiki/twosmallwords.tt",
+            "wiki/whackamole.tt",
+            "wiki/anyone.tt",
+            "tests/pq.tt",
+        ] {
+            let dfa = DFA::from_file(file).unwrap();
+            let rows: Vec<Row> = dfa
+                .to_string()
+                .lines()
+                .map(|r| Row::from_str_custom(r).unwrap())
+                .collect();
+            assert_eq!(DFA::new(rows), dfa, "{}", file);
+            assert!(dfa.to_string().len() <= std::fs::read_to_string(file).unwrap().len());
+        }
+    }
+
+    #[test]
+    fn writer_picks_the_shorter_form() {
+        let dense = "- 0 1 2 0 1 2 0 1 2\n+ 1 E E E E E E E 1 E\n- 2 2 E 2 2 E 2 2 2 E\n";
+        let rows = dense.lines().map(|r| Row::from_str_custom(r).unwrap());
+        assert_eq!(
+            DFA::new(rows.collect()).to_string(),
+            "- 0 1 2 0 1 2 0 1 2 E\n+ 1 7:1 8:E\n- 2 1:E 4:E 8:E *:2\n"
+        );
+    }
+
+    #[test]
+    fn minimal_tables_keep_their_size() {
+        for file in &["wiki/noto.tt", "wiki/endsq.tt", "wiki/twosmallwords.tt"] {
+            let dfa = DFA::from_file(file).unwrap();
+            assert_eq!(dfa.minimize().states(), dfa.states());
+        }
+        let noto = DFA::from_file("wiki/noto.tt").unwrap();
+        assert_eq!(noto.minimize(), noto);
+    }
+
+    #[test]
+    fn minimize_merges_and_drops_rows() {
+        // rows 1 and 2 are equivalent, row 3 is unreachable, row 4 is dead
+        let dfa = DFA::from_table(
+            vec![false, true, true, false, false],
+            vec![
+                vec![Some(1), Some(2), Some(4)],
+                vec![None, None, Some(4)],
+                vec![None, None, None],
+                vec![Some(0), None, None],
+                vec![Some(4), None, None],
+            ],
+        );
+        let expected = DFA::from_table(
+            vec![false, true],
+            vec![vec![Some(1), Some(1), None], vec![None, None, None]],
+        );
+        assert_eq!(dfa.minimize(), expected);
+    }
+
+    #[test]
+    fn minimize_empty_language() {
+        let dfa = DFA::from_table(vec![false, false], vec![vec![Some(1)], vec![Some(0)]]);
+        assert_eq!(
+            dfa.minimize(),
+            DFA::from_table(vec![false], vec![vec![None]])
+        );
+    }
+
+    // keyword tries
+    #[test]
+    fn keywords_share_prefixes_and_suffixes() {
+        let alpha: Alphabet = vec![('a', 0), ('b', 1), ('c', 2)].into_iter().collect();
+        let dfa = DFA::from_keywords(&["ab", "cb", "a"], &alpha).unwrap();
+        let expected = DFA::from_table(
+            vec![false, true, false, true],
+            vec![
+                vec![Some(1), None, Some(2)],
+                vec![None, Some(3), None],
+                vec![None, Some(3), None],
+                vec![None, None, None],
+            ],
+        );
+        assert_eq!(dfa, expected);
+    }
+
+    #[test]
+    fn keywords_outside_alphabet() {
+        let alpha: Alphabet = vec![('a', 0)].into_iter().collect();
+        assert!(DFA::from_keywords(&["ab"], &alpha).is_err());
+    }
+
+    // properties
+    #[test]
+    fn wiki_properties() {
+        let noto = DFA::from_file("wiki/noto.tt").unwrap();
+        assert!(!noto.accepts_empty());
+        assert!(!noto.is_empty());
+        assert!(!noto.is_finite());
+        assert_eq!(noto.max_length(), None);
+
+        let twosmallwords = DFA::from_file("wiki/twosmallwords.tt").unwrap();
+        assert!(!twosmallwords.is_finite());
+
+        let anyone = DFA::from_file("wiki/anyone.tt").unwrap();
+        assert!(anyone.is_finite());
+        assert_eq!(anyone.max_length(), Some(1));
+    }
+
+    #[test]
+    fn finite_with_dead_cycle() {
+        // row 2 loops on itself but can never accept
+        let dfa = DFA::from_table(
+            vec![true, false, false, true],
+            vec![
+                vec![Some(1), Some(2)],
+                vec![Some(3), None],
+                vec![Some(2), Some(2)],
+                vec![None, None],
+            ],
+        );
+        assert!(dfa.accepts_empty());
+        assert!(dfa.is_finite());
+        assert_eq!(dfa.max_length(), Some(2));
+    }
+
+    #[test]
+    fn longest_path_through_shared_rows() {
+        // both 0 -> 1 -> 2 -> 3 and 0 -> 2 -> 3 end in row 3
+        let dfa = DFA::from_table(
+            vec![false, false, false, true],
+            vec![
+                vec![Some(2), Some(1)],
+                vec![Some(2), None],
+                vec![Some(3), None],
+                vec![None, None],
+            ],
+        );
+        assert_eq!(dfa.max_length(), Some(3));
+    }
+
+    #[test]
+    fn empty_languages() {
+        let dfa = DFA::from_table(vec![false, false], vec![vec![Some(1)], vec![Some(0)]]);
+        assert!(dfa.is_empty());
+        assert!(dfa.is_finite());
+        assert_eq!(dfa.max_length(), None);
+
+        let two_liner = DFA::from_file("tests/two_liner.tt").unwrap();
+        assert!(two_liner.is_empty());
+        assert!(DFA::from_file("tests/empty_file.tt").unwrap().is_empty());
+    }
+
+    // equivalence and inclusion
+    #[test]
+    fn minimized_tables_are_equivalent() {
+        let dfa = DFA::from_table(
+            vec![false, true, true, false, false],
+            vec![
+                vec![Some(1), Some(2), Some(4)],
+                vec![None, None, Some(4)],
+                vec![None, None, None],
+                vec![Some(0), None, None],
+                vec![Some(4), None, None],
+            ],
+        );
+        assert_eq!(dfa.equivalent(&dfa.minimize()), Ok(()));
+        let twosmallwords = DFA::from_file("wiki/twosmallwords.tt").unwrap();
+        assert_eq!(twosmallwords.equivalent(&twosmallwords.minimize()), Ok(()));
+    }
+
+    #[test]
+    fn shortest_distinguishing_text() {
+        let noto = DFA::from_file("wiki/noto.tt").unwrap();
+        let nots = DFA::from_file("wiki/nots.tt").unwrap();
+        // "o" is only accepted by nots
+        assert_eq!(noto.equivalent(&nots), Err(vec![3]));
+        assert_eq!(nots.equivalent(&noto), Err(vec![3]));
+        assert_eq!(noto.is_subset_of(&nots), Err(vec![7]));
+        assert!(!nots.accepts(&[7]));
+        assert!(noto.accepts(&[7]));
+    }
+
+    #[test]
+    fn subsets() {
+        let noto = DFA::from_file("wiki/noto.tt").unwrap();
+        let pq = DFA::from_file("tests/pq.tt").unwrap();
+        assert_eq!(pq.is_subset_of(&noto), Ok(()));
+        assert_eq!(noto.is_subset_of(&pq), Err(vec![6]));
+        assert_eq!(noto.equivalent(&pq), Err(vec![6]));
+    }
+
+    #[test]
+    fn empty_text_distinguishes() {
+        let empty = DFA::from_file("tests/empty_string.tt").unwrap();
+        let optional = DFA::from_file("tests/optional_a.tt").unwrap();
+        assert_eq!(empty.is_subset_of(&optional), Ok(()));
+        assert_eq!(optional.equivalent(&empty), Err(vec![1]));
+        assert_eq!(
+            DFA::from_file("tests/empty_file.tt")
+                .unwrap()
+                .equivalent(&empty),
+            Err(vec![])
+        );
+    }
+
+    // boolean operations
+    #[test]
+    fn union_and_intersection() {
+        let noto = DFA::from_file("wiki/noto.tt").unwrap();
+        let nots = DFA::from_file("wiki/nots.tt").unwrap();
+        let either = noto.union(&nots);
+        let both = noto.intersection(&nots);
+        assert!(either.accepts(&[3, 4]) && either.accepts(&[7, 4]));
+        assert!(!either.accepts(&[3, 7]));
+        assert!(both.accepts(&[4, 5, 6]));
+        assert!(!both.accepts(&[3]) && !both.accepts(&[7]));
+        assert_eq!(both.is_subset_of(&either), Ok(()));
+    }
+
+    #[test]
+    fn difference_removes_keywords() {
+        let noto = DFA::from_file("wiki/noto.tt").unwrap();
+        let pq = DFA::from_file("tests/pq.tt").unwrap();
+        let rest = noto.difference(&pq);
+        assert!(!rest.accepts(&[4]) && !rest.accepts(&[4, 5]));
+        assert!(rest.accepts(&[5, 4]) && rest.accepts(&[4, 4]));
+        assert_eq!(rest.union(&pq).equivalent(&noto), Ok(()));
+    }
+
+    #[test]
+    fn complement_is_everything_else() {
+        let anyone = DFA::from_file("wiki/anyone.tt").unwrap();
+        let others = anyone.complement(8);
+        assert!(others.accepts(&[]));
+        assert!(!others.accepts(&[3]));
+        assert!(others.accepts(&[3, 3]));
+        assert_eq!(others.complement(8).equivalent(&anyone), Ok(()));
+        assert!(others.intersection(&anyone).is_empty());
+    }
+
+    // composition
+    #[test]
+    fn twosmallwords_repeated() {
+        let words = DFA::from_file("wiki/twosmallwords.tt").unwrap();
+        let once: Vec<usize> = vec![3, 4, 5, 6, 1, 3, 4, 5, 1];
+        let twice: Vec<usize> = once.iter().chain(once.iter()).copied().collect();
+        assert!(words.accepts(&once) && !words.accepts(&twice));
+        assert!(words.plus().accepts(&twice) && !words.plus().accepts(&[]));
+        assert!(words.star().accepts(&twice) && words.star().accepts(&[]));
+        assert!(words.concat(&words).accepts(&twice));
+        assert!(!words.concat(&words).accepts(&once));
+        assert_eq!(
+            words.plus().equivalent(&words.concat(&words.star())),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn optional_and_reverse() {
+        let pq = DFA::from_file("tests/pq.tt").unwrap();
+        assert!(pq.optional().accepts(&[]) && pq.optional().accepts(&[4, 5]));
+        assert!(pq.reverse().accepts(&[5, 4]) && !pq.reverse().accepts(&[4, 5]));
+        assert_eq!(pq.reverse().reverse().equivalent(&pq), Ok(()));
+    }
+
+    // enumerating
+    #[test]
+    fn pq_words() {
+        let pq = DFA::from_file("tests/pq.tt").unwrap();
+        let words: Vec<Vec<usize>> = pq.words().collect();
+        assert_eq!(words, vec![vec![4], vec![5], vec![4, 5]]);
+    }
+
+    #[test]
+    fn long_texts_come_quickly() {
+        // 8^40 texts of length 40, a breadth first search never gets there
+        let one = DFA::from_file("wiki/anyone.tt").unwrap();
+        let forty = (1..40).fold(one.clone(), |dfa, _| dfa.concat(&one));
+        let mut words = forty.words();
+        assert_eq!(words.next(), Some(vec![0; 40]));
+        let mut ninth = vec![0; 40];
+        ninth[38] = 1;
+        assert_eq!(words.nth(7), Some(ninth));
+    }
+
+    #[test]
+    fn twosmallwords_strings() {
+        let alpha: Alphabet = "\n \\opqrs".chars().zip(0..).collect();
+        let words = DFA::from_file("wiki/twosmallwords.tt").unwrap();
+        let strings: Vec<String> = words.strings(&alpha).take(3).collect();
+        assert_eq!(strings, vec!["ooo ooo ", "ooo oop ", "ooo ooq "]);
+        assert!(words.strings(&alpha).take(50).all(|s| s.ends_with(' ')));
+    }
+
+    #[test]
+    fn other_column_strings() {
+        let mut alpha: Alphabet = "\n \\opqrs".chars().zip(0..).collect();
+        alpha.set_other(8);
+        let comment = DFA::from_file("tests/other_comment.tt").unwrap();
+        let strings: Vec<String> = comment.strings(&alpha).take(9).collect();
+        assert_eq!(strings[0], "\\");
+        assert_eq!(strings[8], "\\!");
+        let covering = comment.covering_strings(&alpha);
+        assert!(covering.contains(&("\\!".to_string(), true)));
+        assert!(covering.iter().all(|(text, _)| text.starts_with('\\')));
+    }
+
+    #[test]
+    fn shortlex_with_empty_text() {
+        let alpha: Alphabet = vec![('a', 0), ('b', 1)].into_iter().collect();
+        let optional = DFA::from_file("tests/optional_a.tt").unwrap();
+        let strings: Vec<String> = optional.strings(&alpha).collect();
+        assert_eq!(strings, vec!["", "b"]);
+        assert_eq!(
+            DFA::from_file("tests/two_liner.tt").unwrap().words().next(),
+            None
+        );
+    }
+
+    // counting and sampling
+    #[test]
+    fn pq_counts() {
+        let pq = DFA::from_file("tests/pq.tt").unwrap();
+        let counts: Vec<u128> = (0..4).map(|n| pq.count_of_length(n)).collect();
+        assert_eq!(counts, vec![0, 2, 1, 0]);
+        assert_eq!(DFA::default().count_of_length(3), 0);
+    }
+
+    #[test]
+    fn counts_saturate() {
+        // 8 columns, so 8^n texts of length n
+        let anything = DFA::from_file("wiki/anyone.tt").unwrap().star();
+        assert_eq!(anything.count_of_length(3), 512);
+        assert_eq!(anything.count_of_length(42), 1 << 126);
+        assert_eq!(anything.count_of_length(43), u128::MAX);
+    }
+
+    #[test]
+    fn samples_are_accepted() {
+        let pq = DFA::from_file("tests/pq.tt").unwrap();
+        let mut random = Random::new(35);
+        assert_eq!(pq.sample(2, &mut random), Some(vec![4, 5]));
+        assert_eq!(pq.sample(3, &mut random), None);
+        let mut seen = [0; 2];
+        for _ in 0..200 {
+            let word = pq.sample(1, &mut random).unwrap();
+            seen[word[0] - 4] += 1;
+        }
+        assert!(seen[0] > 50 && seen[1] > 50);
+
+        let words = DFA::from_file("wiki/twosmallwords.tt").unwrap().plus();
+        for length in [8, 16, 100] {
+            let word = words.sample(length, &mut random).unwrap();
+            assert_eq!(word.len(), length);
+            assert!(words.accepts(&word));
+        }
+    }
+
+    #[test]
+    fn same_seed_same_sample() {
+        let words = DFA::from_file("wiki/twosmallwords.tt").unwrap().plus();
+        let a = words.sample(24, &mut Random::new(1));
+        assert_eq!(a, words.sample(24, &mut Random::new(1)));
+    }
+
+    // covering texts
+    #[test]
+    fn pq_covering_words() {
+        let pq = DFA::from_file("tests/pq.tt").unwrap();
+        assert_eq!(
+            pq.covering_words(),
+            vec![(vec![4], true), (vec![5], true), (vec![4, 5], true)]
+        );
+        let optional = DFA::from_file("tests/optional_a.tt").unwrap();
+        assert_eq!(
+            optional.covering_words(),
+            vec![(vec![], true), (vec![1], true)]
+        );
+    }
+
+    #[test]
+    fn covering_words_never_accepted() {
+        let never = DFA::from_file("tests/never_matches.tt").unwrap();
+        assert_eq!(
+            never.covering_words(),
+            vec![(vec![1], false), (vec![1, 0], false)]
+        );
+    }
+
+    #[test]
+    fn covering_words_take_every_transition() {
+        for file in [
+            "noto",
+            "nots",
+            "endsq",
+            "twosmallwords",
+            "whackamole",
+            "anyone",
+        ] {
+            let dfa = DFA::from_file(format!("wiki/{}.tt", file)).unwrap();
+            let words = dfa.covering_words();
+            let mut taken = vec![vec![false; dfa.columns()]; dfa.states()];
+            for (word, accepted) in words.iter() {
+                assert_eq!(dfa.accepts(word), *accepted);
+                let mut state = 0;
+                for &letter in word {
+                    taken[state][letter] = true;
+                    state = dfa.next(state, letter).unwrap();
+                }
+            }
+            let reachable = dfa.reachable();
+            for row in (0..dfa.states()).filter(|&row| reachable[row]) {
+                for (letter, &taken) in taken[row].iter().enumerate() {
+                    assert_eq!(taken, dfa.next(row, letter).is_some());
+                }
+            }
+            assert!(words.len() <= dfa.states() * dfa.columns());
+        }
+    }
+
     // Tests for str_parse
     #[test]
     #[should_panic]
@@ -166,6 +1533,37 @@ mod test {
         assert_eq!(r, Row::new(false, 1, vec![Some(2), None, None]));
     }
 
+    #[test]
+    fn sparse_str_parse() {
+        let r = Row::from_str_custom("- 0 1:1 3-4:2").unwrap();
+        assert_eq!(
+            r,
+            Row::new(false, 0, vec![None, Some(1), None, Some(2), Some(2)])
+        );
+        let r = Row::from_str_custom("+ 2 0:E 2:E *:1").unwrap();
+        assert_eq!(r.transitions(), &[None, Some(1), None]);
+        assert_eq!(r.rest, Some(1));
+
+        // * reaches the columns past the end of the row too
+        let dfa = DFA::new(vec![r, Row::from_str_custom("- 0 5:E").unwrap()]);
+        assert_eq!(dfa.columns(), 6);
+        assert_eq!(dfa.transition(0, 5), Some(1));
+        assert_eq!(dfa.transition(1, 0), None);
+
+        assert!(Row::from_str_custom("- 0 4-2:1").is_err());
+        assert!(Row::from_str_custom("- 0 1:x").is_err());
+        assert!(Row::from_str_custom("- 0 a:1").is_err());
+    }
+
+    #[test]
+    fn sparse_columns_listed_once() {
+        assert!(Row::from_str_custom("- 0 0-5:1 3:2").is_err());
+        assert!(Row::from_str_custom("- 0 2:1 2:E").is_err());
+        assert!(Row::from_str_custom("- 0 3-4:1 0-3:2").is_err());
+        assert!(Row::from_str_custom("- 0 1:1 *:E *:2").is_err());
+        assert!(Row::from_str_custom("- 0 3-4:1 0-2:2 5:E").is_ok());
+    }
+
     #[test]
     fn accepting_and_another_id() {
         let r = Row::from_str_custom("+ 1 2 E E").unwrap();
@@ -1,88 +1,570 @@
-use crate::dfa::DFA;
-// use crate::driver::Driver;
 use crate::alphabet_translator::alphabet_translator::char_to_hex_a_string;
-use crate::regex::Regex;
-use crate::scanner::Scanner;
+use crate::coverage::Coverage;
+use crate::regex::{Regex, Traced};
+use crate::scanner::{Alphabet, Scanner, TransitionTable};
+use std::cmp::Reverse;
+use std::fmt;
 use std::fs::File;
-use std::fs::OpenOptions;
 use std::io::prelude::*;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
+
+/// How the driver picks a token when several transition tables match at the
+/// start of the remaining source. Ties between tables are broken by the
+/// higher `%priority`, then by which table is listed first in the scanner
+/// definition file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MatchPolicy {
+    /// The longest match wins
+    #[default]
+    MaximalMunch,
+    /// The highest priority table that matches at all wins, using its longest match
+    FirstDefined,
+    /// Each table only matches its shortest prefix, and the shortest of those wins
+    Shortest,
+}
+
+impl FromStr for MatchPolicy {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "maximal-munch" => Ok(MatchPolicy::MaximalMunch),
+            "first-defined" => Ok(MatchPolicy::FirstDefined),
+            "shortest" => Ok(MatchPolicy::Shortest),
+            _ => Err(format!("unknown match policy: {}", input)),
+        }
+    }
+}
+
+impl fmt::Display for MatchPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MatchPolicy::MaximalMunch => write!(f, "maximal-munch"),
+            MatchPolicy::FirstDefined => write!(f, "first-defined"),
+            MatchPolicy::Shortest => write!(f, "shortest"),
+        }
+    }
+}
+
+/// One line of the tokenized output
+/// id value line column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub id: String,
+    pub value: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Token {
+    pub fn new(id: String, value: String, line: usize, column: usize) -> Self {
+        Self {
+            id,
+            value,
+            line,
+            column,
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.id, self.value, self.line, self.column
+        )
+    }
+}
+
+impl FromStr for Token {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = input.split_whitespace().collect();
+        match fields.as_slice() {
+            [id, value, line, column] => Ok(Token::new(
+                id.to_string(),
+                value.to_string(),
+                line.parse()
+                    .map_err(|_| format!("invalid line: {}", input))?,
+                column
+                    .parse()
+                    .map_err(|_| format!("invalid column: {}", input))?,
+            )),
+            _ => Err(format!("invalid token: {}", input)),
+        }
+    }
+}
+
+/// Why scanning stopped before the end of the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LexErrorKind {
+    NotInAlphabet,
+    NoMatch,
+}
+
+/// Where and why scanning stopped, with the tokens scanned before that
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub letter: char,
+    pub line: usize,
+    pub column: usize,
+    /// The tables that took some of the text before running into E, as
+    /// (id, chars taken), furthest first
+    pub partial: Vec<(String, usize)>,
+    pub tokens: Vec<Token>,
+}
+
+impl LexError {
+    fn message(&self) -> String {
+        let letter = char_to_hex_a_string(&self.letter.to_string());
+        match self.kind {
+            LexErrorKind::NotInAlphabet => format!("{} is not in the alphabet", letter),
+            LexErrorKind::NoMatch => format!("no token matches at {}", letter),
+        }
+    }
+
+    /// The error the way rustc shows one, with the line of the source it is
+    /// on. Lines end at the scanner's newline char, as the line numbers do.
+    pub fn render(&self, file_name: &str, source: &str, newline: char) -> String {
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let text = source.split(newline).nth(self.line - 1).unwrap_or("");
+        let caret = " ".repeat(self.column - 1);
+
+        let mut rendered = format!(
+            "error: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}^\n",
+            self.message(),
+            gutter,
+            file_name,
+            self.line,
+            self.column,
+            gutter,
+            number,
+            text,
+            gutter,
+            caret
+        );
+        for (id, taken) in self.partial.iter() {
+            let chars = if *taken == 1 { "char" } else { "chars" };
+            rendered.push_str(&format!(
+                "{} = note: {} took {} {} before running into E\n",
+                gutter, id, taken, chars
+            ));
+        }
+        rendered
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message())
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// A match of one transition table at the start of the remaining source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Candidate {
+    index: usize,
+    length: usize,
+    newlines: usize,
+    column: usize,
+}
+
 pub struct Driver {
     // regexes: Vec<Regex>,
 }
 
 impl Driver {
-    pub fn run(scanner: Scanner, src_file: PathBuf, out_path: PathBuf) {
-        let mut source =
-            std::fs::read_to_string(src_file).unwrap_or_else(|_| std::process::exit(6)); // TODO fix the crate path
+    /// Writes the tokens to the output file, and why each was picked to the
+    /// trace if there is one
+    pub fn run(
+        scanner: Scanner,
+        src_file: PathBuf,
+        out_path: PathBuf,
+        trace: Option<Box<dyn Write>>,
+    ) {
+        let source = std::fs::read_to_string(&src_file).unwrap_or_else(|_| std::process::exit(6)); // TODO fix the crate path
+
+        let result = match trace {
+            Some(mut trace) => Driver::tokenize_with_trace(&scanner, &source, trace.as_mut())
+                .unwrap_or_else(|_| std::process::exit(4)),
+            None => Driver::tokenize(&scanner, &source),
+        };
+        // The tokens before an error are still written out
+        let tokens = match &result {
+            Ok(tokens) => tokens,
+            Err(e) => &e.tokens,
+        };
 
         let mut output_file = File::create(out_path).unwrap_or_else(|_| std::process::exit(4));
-        // let mut output_file = OpenOptions::new()
-        //     .create_new(true)
-        //     .write(true)
-        //     .append(true)
-        //     .open(out_path)
-        //     .unwrap();
-        let mut regxs: Vec<Regex> = Vec::new();
+        for token in tokens {
+            writeln!(output_file, "{}", token).unwrap_or_else(|_| std::process::exit(4));
+        }
+
+        if let Err(e) = result {
+            eprint!(
+                "{}",
+                e.render(&src_file.display().to_string(), &source, scanner.newline)
+            );
+            match e.kind {
+                LexErrorKind::NotInAlphabet => std::process::exit(42),
+                LexErrorKind::NoMatch => std::process::exit(7),
+            }
+        }
+    }
+
+    /// Splits the source into tokens. Fails at the first position where no
+    /// transition table matches.
+    pub fn tokenize(scanner: &Scanner, source: &str) -> Result<Vec<Token>, LexError> {
+        Driver::scan(scanner, source, None, None).0
+    }
+
+    /// Like `tokenize`, also counting the transitions each table takes
+    pub fn tokenize_with_coverage(
+        scanner: &Scanner,
+        source: &str,
+        coverage: &mut Coverage,
+    ) -> Result<Vec<Token>, LexError> {
+        Driver::scan(scanner, source, Some(coverage), None).0
+    }
+
+    /// Like `tokenize`, also writing down for each token how every table
+    /// matched and why the winner won. Fails if the trace can't be written.
+    pub fn tokenize_with_trace(
+        scanner: &Scanner,
+        source: &str,
+        trace: &mut dyn Write,
+    ) -> std::io::Result<Result<Vec<Token>, LexError>> {
+        let (result, written) = Driver::scan(scanner, source, None, Some(trace));
+        written?;
+        trace.flush()?;
+        Ok(result)
+    }
+
+    /// Tokenizes the source, with whether the trace, if any, was written.
+    /// The trace stops at the first write that fails.
+    fn scan(
+        scanner: &Scanner,
+        source: &str,
+        coverage: Option<&mut Coverage>,
+        mut trace: Option<&mut dyn Write>,
+    ) -> (Result<Vec<Token>, LexError>, std::io::Result<()>) {
         let alpha = scanner.get_alpha();
         let trans = scanner.get_trans();
-        //println!("{}", source);
         // Create all regexs
-        for t in trans {
-            let replace_value = match &t.replace_with {
-                None => None,
-                Some(t) => Some(t.to_string()),
-            };
-            regxs.push(Regex::new(&t.tt, Some(&t.id), &alpha, replace_value));
-        }
-
-        let mut line_number = 1;
-        let mut position = 1;
-        let mut previos_pos = 1;
-        let mut prev_line = 1;
-        loop {
-            let mut longest = 0;
-            let mut num_newlines = 0;
-            let mut regex_id = "";
-            let mut regex_replace_value: Option<&String> = None;
-            for r in regxs.iter() {
-                let (length, newlines, char_number) =
-                    r.first_match(source.as_str(), scanner.newline);
-                if length > longest {
-                    num_newlines = newlines;
-                    longest = length;
-                    position = char_number;
-                    regex_id = r.token.unwrap();
-                    regex_replace_value = r.replace_with.as_ref();
+        let regxs: Vec<Regex> = trans
+            .iter()
+            .map(|t| Regex::new(&t.tt, Some(&t.id), &alpha, t.replace_with.clone()))
+            .collect();
+        if coverage.is_some() {
+            regxs.iter().for_each(Regex::record);
+        }
+
+        let mut tokens = Vec::new();
+        let mut source = source;
+        let mut line = 1;
+        let mut column = 1;
+        let mut error = None;
+        let mut written = Ok(());
+        while !source.is_empty() {
+            let best = match trace.as_deref_mut() {
+                Some(out) => {
+                    let (best, traced) = Driver::choose_with_trace(
+                        &regxs,
+                        trans,
+                        scanner,
+                        source,
+                        (line, column),
+                        out,
+                    );
+                    if let Err(e) = traced {
+                        written = Err(e);
+                    }
+                    best
                 }
+                None => Driver::choose(&regxs, trans, scanner.policy, source, scanner.newline),
+            };
+            if written.is_err() {
+                trace = None;
             }
-            line_number += num_newlines;
-            let hex_encoded_output = char_to_hex_a_string(&source[..longest].to_string());
-            let line = format!(
-                "{} {} {} {}",
-                regex_id,
-                regex_replace_value.unwrap_or(&hex_encoded_output),
-                prev_line,
-                previos_pos
-            );
-            // println!("{}", line);
-            writeln!(output_file, "{}", line);
-            prev_line = line_number;
-            if num_newlines == 0 {
-                previos_pos += longest;
+            let best = match best {
+                Some(best) => best,
+                None => {
+                    error = Some(Driver::error(&regxs, trans, &alpha, source, line, column));
+                    break;
+                }
+            };
+            let lexeme = &source[..best.length];
+            tokens.push(Driver::token(&trans[best.index], lexeme, line, column));
+
+            line += best.newlines;
+            if best.newlines == 0 {
+                column += lexeme.chars().count();
             } else {
-                previos_pos = position;
+                column = best.column;
             }
-            source = source[longest..].to_string(); // chop off what we tokenized
-            if source.len() == 0 {
-                break;
+            source = &source[best.length..]; // chop off what we tokenized
+        }
+
+        if let Some(coverage) = coverage {
+            for (index, r) in regxs.iter().enumerate() {
+                coverage.add(index, r.take_visits());
             }
         }
+        let result = match error {
+            Some(error) => Err(LexError { tokens, ..error }),
+            None => Ok(tokens),
+        };
+        (result, written)
+    }
 
-        //let crate::driver::Driver::make_output(&regxs, &mut source);
-        //println!("{}", str_ohea);
+    /// The error for the start of the input, where no table matches
+    fn error(
+        regxs: &[Regex],
+        trans: &[TransitionTable],
+        alpha: &Alphabet,
+        input: &str,
+        line: usize,
+        column: usize,
+    ) -> LexError {
+        let letter = input.chars().next().unwrap();
+        let kind = if alpha.contains_key(&letter) {
+            LexErrorKind::NoMatch
+        } else {
+            LexErrorKind::NotInAlphabet
+        };
+        let mut partial: Vec<(String, usize)> = regxs
+            .iter()
+            .zip(trans.iter())
+            .map(|(r, t)| {
+                (
+                    t.id.clone(),
+                    r.run_length(input).unwrap_or(input.chars().count()),
+                )
+            })
+            .filter(|&(_, taken)| taken > 0)
+            .collect();
+        partial.sort_by_key(|&(_, taken)| Reverse(taken));
+
+        LexError {
+            kind,
+            letter,
+            line,
+            column,
+            partial,
+            tokens: vec![],
+        }
+    }
+
+    /// Like `choose`, also writing down how each table matches at the start
+    /// of the input, where it ran into E, and why the best one won. Each
+    /// table reads the input once, and lengths are in chars.
+    fn choose_with_trace(
+        regxs: &[Regex],
+        trans: &[TransitionTable],
+        scanner: &Scanner,
+        input: &str,
+        (line, column): (usize, usize),
+        trace: &mut dyn Write,
+    ) -> (Option<Candidate>, std::io::Result<()>) {
+        let shortest = scanner.policy == MatchPolicy::Shortest;
+        let traced: Vec<_> = regxs
+            .iter()
+            .map(|r| r.traced_match(input, scanner.newline, shortest))
+            .collect();
+        let candidates: Vec<Candidate> = traced
+            .iter()
+            .enumerate()
+            .map(|(index, &((length, newlines, column), _, _))| Candidate {
+                index,
+                length,
+                newlines,
+                column,
+            })
+            .collect();
+        let best = Driver::pick(
+            trans,
+            scanner.policy,
+            candidates.iter().map(|c| (c.index, c.length)),
+        )
+        .map(|(index, _)| candidates[index]);
+
+        let written = Driver::trace(
+            trans,
+            scanner.policy,
+            &traced,
+            best.map(|b| b.index),
+            (line, column),
+            trace,
+        );
+        (best, written)
+    }
+
+    /// Writes the winner and how each table did, from what `traced_match`
+    /// found for it
+    fn trace(
+        trans: &[TransitionTable],
+        policy: MatchPolicy,
+        traced: &[Traced],
+        best: Option<usize>,
+        (line, column): (usize, usize),
+        trace: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        let chars: Vec<(usize, usize)> = traced
+            .iter()
+            .enumerate()
+            .map(|(index, &(_, chars, _))| (index, chars))
+            .collect();
+        match best {
+            Some(best) => writeln!(
+                trace,
+                "{}:{} {} wins, {}",
+                line,
+                column,
+                trans[best].id,
+                Driver::reason(trans, policy, &chars, best)
+            )?,
+            None => writeln!(trace, "{}:{} no table matches", line, column)?,
+        }
+        for (index, &(_, length, run)) in traced.iter().enumerate() {
+            let died = match run {
+                Some(run) => format!("dies on char {}", run + 1),
+                None => "reaches the end".to_string(),
+            };
+            writeln!(trace, "  {}: match {}, {}", trans[index].id, length, died)?;
+        }
+        Ok(())
+    }
+
+    /// Why the best match beat the others under the policy, from the
+    /// (table, chars) matches
+    fn reason(
+        trans: &[TransitionTable],
+        policy: MatchPolicy,
+        matches: &[(usize, usize)],
+        best: usize,
+    ) -> String {
+        let length = matches[best].1;
+        let (rule, ties) = match policy {
+            MatchPolicy::MaximalMunch => ("longest match", "tied with"),
+            MatchPolicy::FirstDefined => ("first defined match", "also matched by"),
+            MatchPolicy::Shortest => ("shortest match", "tied with"),
+        };
+        let others: Vec<usize> = matches
+            .iter()
+            .filter(|&&(index, taken)| {
+                index != best
+                    && taken > 0
+                    && (policy == MatchPolicy::FirstDefined || taken == length)
+            })
+            .map(|&(index, _)| index)
+            .collect();
+        if others.is_empty() {
+            return format!("{} of {}", rule, length);
+        }
+
+        let ids: Vec<&str> = others.iter().map(|&o| trans[o].id.as_str()).collect();
+        let by = if others
+            .iter()
+            .all(|&o| trans[o].priority < trans[best].priority)
+        {
+            "higher priority"
+        } else {
+            "being listed first"
+        };
+        format!(
+            "{} of {}, {} {}, wins on {}",
+            rule,
+            length,
+            ties,
+            ids.join(", "),
+            by
+        )
+    }
+
+    /// The token a table produces for a lexeme it won
+    pub fn token(table: &TransitionTable, lexeme: &str, line: usize, column: usize) -> Token {
+        // Exact matches in the winner's keyword table become keywords
+        match table.keywords.as_ref().and_then(|k| k.reclassify(lexeme)) {
+            Some(keyword_id) => Token::new(
+                keyword_id.to_string(),
+                char_to_hex_a_string(lexeme),
+                line,
+                column,
+            ),
+            None => Token::new(
+                table.id.clone(),
+                table
+                    .replace_with
+                    .clone()
+                    .unwrap_or_else(|| char_to_hex_a_string(lexeme)),
+                line,
+                column,
+            ),
+        }
+    }
+
+    /// Picks the winning match at the start of the input according to the policy
+    fn choose(
+        regxs: &[Regex],
+        trans: &[TransitionTable],
+        policy: MatchPolicy,
+        input: &str,
+        newline: char,
+    ) -> Option<Candidate> {
+        let candidates: Vec<Candidate> = regxs
+            .iter()
+            .enumerate()
+            .map(|(index, r)| {
+                let (length, newlines, column) = match policy {
+                    MatchPolicy::Shortest => r.shortest_match(input, newline),
+                    _ => r.first_match(input, newline),
+                };
+                Candidate {
+                    index,
+                    length,
+                    newlines,
+                    column,
+                }
+            })
+            .collect();
+
+        let (index, _) = Driver::pick(
+            trans,
+            policy,
+            candidates.iter().map(|c| (c.index, c.length)),
+        )?;
+        Some(candidates[index])
+    }
+
+    /// Picks the winner among (table, length) matches according to the
+    /// policy. Empty matches never win.
+    pub fn pick(
+        trans: &[TransitionTable],
+        policy: MatchPolicy,
+        matches: impl Iterator<Item = (usize, usize)>,
+    ) -> Option<(usize, usize)> {
+        let matches = matches.filter(|&(_, length)| length > 0);
+        match policy {
+            MatchPolicy::MaximalMunch => {
+                matches.max_by_key(|&(t, length)| (length, Driver::rank(trans, t)))
+            }
+            MatchPolicy::FirstDefined => matches.max_by_key(|&(t, _)| Driver::rank(trans, t)),
+            MatchPolicy::Shortest => {
+                matches.min_by_key(|&(t, length)| (length, Reverse(Driver::rank(trans, t))))
+            }
+        }
+    }
+
+    /// How a table fares in a tie, the higher rank wins
+    pub fn rank(trans: &[TransitionTable], index: usize) -> (i32, Reverse<usize>) {
+        (trans[index].priority, Reverse(index))
     }
 
     /*pub fn make_output(regexs: &Vec<Regex>, source: &mut String) -> String {
@@ -105,3 +587,271 @@ impl Driver {
         });
     }*/
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dfa::DFA;
+
+    fn scanner(files: &[(&str, &str, i32)], policy: MatchPolicy) -> Scanner {
+        let (alpha, newline) = Scanner::alphabet_build("x0ax20x5C x6fpqrx73").unwrap();
+        let trans = files
+            .iter()
+            .map(|(file, id, priority)| {
+                let mut t =
+                    TransitionTable::new(DFA::from_file(file).unwrap(), id.to_string(), None);
+                t.priority = *priority;
+                t
+            })
+            .collect();
+        let mut sc = Scanner::new(newline, alpha, trans);
+        sc.policy = policy;
+        sc
+    }
+
+    fn traced(sc: &Scanner, source: &str) -> (Result<Vec<Token>, LexError>, String) {
+        let mut trace = vec![];
+        let result = Driver::tokenize_with_trace(sc, source, &mut trace).unwrap();
+        (result, String::from_utf8(trace).unwrap())
+    }
+
+    fn ids(tokens: &[Token]) -> Vec<&str> {
+        tokens.iter().map(|t| t.id.as_str()).collect()
+    }
+
+    #[test]
+    fn wiki_example_positions() {
+        let sc = Scanner::from_file("wiki/scan.u").unwrap();
+        let source = std::fs::read_to_string("wiki/source.txt").unwrap();
+        let expected = std::fs::read_to_string("wiki/tokens.dat").unwrap();
+
+        let tokens = Driver::tokenize(&sc, &source).unwrap();
+        let expected: Vec<(String, usize, usize)> = expected
+            .lines()
+            .map(|l| {
+                let fields: Vec<&str> = l.split_whitespace().collect();
+                (
+                    fields[0].to_string(),
+                    fields[2].parse().unwrap(),
+                    fields[3].parse().unwrap(),
+                )
+            })
+            .collect();
+        let actual: Vec<(String, usize, usize)> = tokens
+            .into_iter()
+            .map(|t| (t.id, t.line, t.column))
+            .collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn replace_with_and_keywords() {
+        let sc = Scanner::from_file("tests/keywords.u").unwrap();
+        let tokens = Driver::tokenize(&sc, "pqrs pq").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new("PQRS".into(), "x70x71x72x73".into(), 1, 1),
+                Token::new("IGNORE".into(), "x20".into(), 1, 5),
+                Token::new("pqrs".into(), "x70x71".into(), 1, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn maximal_munch_ties_go_to_priority_then_order() {
+        // same priority, so the one listed first wins
+        let files = [("wiki/noto.tt", "pqrs", 0), ("wiki/nots.tt", "opqr", 0)];
+        let sc = scanner(&files, MatchPolicy::MaximalMunch);
+        assert_eq!(ids(&Driver::tokenize(&sc, "pqr").unwrap()), vec!["pqrs"]);
+        assert_eq!(ids(&Driver::tokenize(&sc, "pqrs").unwrap()), vec!["pqrs"]);
+        assert_eq!(ids(&Driver::tokenize(&sc, "pqro").unwrap()), vec!["opqr"]);
+
+        // a higher priority wins even when listed second
+        let files = [("wiki/noto.tt", "pqrs", 0), ("wiki/nots.tt", "opqr", 1)];
+        let sc = scanner(&files, MatchPolicy::MaximalMunch);
+        assert_eq!(ids(&Driver::tokenize(&sc, "pqr").unwrap()), vec!["opqr"]);
+        // a longer match still beats a higher priority
+        assert_eq!(ids(&Driver::tokenize(&sc, "pqrs").unwrap()), vec!["pqrs"]);
+    }
+
+    #[test]
+    fn first_defined_ignores_length() {
+        let files = [("wiki/nots.tt", "opqr", 0), ("wiki/noto.tt", "pqrs", 0)];
+        let sc = scanner(&files, MatchPolicy::FirstDefined);
+        let tokens = Driver::tokenize(&sc, "pqrs").unwrap();
+        assert_eq!(ids(&tokens), vec!["opqr", "pqrs"]);
+        assert_eq!(tokens[1].column, 4);
+
+        let files = [("wiki/nots.tt", "opqr", 0), ("wiki/noto.tt", "pqrs", 1)];
+        let sc = scanner(&files, MatchPolicy::FirstDefined);
+        assert_eq!(ids(&Driver::tokenize(&sc, "pqrs").unwrap()), vec!["pqrs"]);
+    }
+
+    #[test]
+    fn shortest_matches() {
+        let files = [("wiki/endsq.tt", "endsq", 0), ("wiki/noto.tt", "pqrs", 0)];
+        let sc = scanner(&files, MatchPolicy::Shortest);
+        let tokens = Driver::tokenize(&sc, "qpq").unwrap();
+        assert_eq!(ids(&tokens), vec!["endsq", "pqrs", "endsq"]);
+
+        let files = [("wiki/endsq.tt", "endsq", 0), ("wiki/noto.tt", "pqrs", 1)];
+        let sc = scanner(&files, MatchPolicy::Shortest);
+        assert_eq!(
+            ids(&Driver::tokenize(&sc, "qpq").unwrap()),
+            vec!["pqrs", "pqrs", "pqrs"]
+        );
+    }
+
+    #[test]
+    fn stops_when_nothing_matches() {
+        let files = [("wiki/noto.tt", "pqrs", 0)];
+        let sc = scanner(&files, MatchPolicy::MaximalMunch);
+        let e = Driver::tokenize(&sc, "pp o pp").unwrap_err();
+        assert_eq!(ids(&e.tokens), vec!["pqrs"]);
+        assert_eq!(
+            (e.kind, e.letter, e.line, e.column),
+            (LexErrorKind::NoMatch, ' ', 1, 3)
+        );
+        assert_eq!(Driver::tokenize(&sc, "").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn not_in_the_alphabet() {
+        let sc = Scanner::from_file("wiki/scan.u").unwrap();
+        let e = Driver::tokenize(&sc, "pp\npp$pq").unwrap_err();
+        assert_eq!(e.kind, LexErrorKind::NotInAlphabet);
+        assert_eq!((e.letter, e.line, e.column), ('$', 2, 3));
+        assert_eq!(e.partial, vec![]);
+        assert_eq!(ids(&e.tokens), vec!["pqrs", "IGNORE", "pqrs"]);
+        assert_eq!(e.to_string(), "2:3: x24 is not in the alphabet");
+    }
+
+    #[test]
+    fn other_column_takes_the_rest() {
+        let sc = Scanner::from_file("tests/other.u").unwrap();
+        let tokens = Driver::tokenize(&sc, "pq \\ $ü\tzs\nrs").unwrap();
+        assert_eq!(
+            ids(&tokens),
+            vec!["word", "IGNORE", "comment", "IGNORE", "word"]
+        );
+        assert_eq!(tokens[2].value, "x5Cx20x24xFCx09x7Ax73");
+        // the other column only follows \ in a comment
+        let e = Driver::tokenize(&sc, "pq$").unwrap_err();
+        assert_eq!(
+            (e.kind, e.letter, e.column),
+            (LexErrorKind::NoMatch, '$', 3)
+        );
+    }
+
+    #[test]
+    fn class_columns() {
+        let sc = Scanner::from_file("tests/classes.u").unwrap();
+        let tokens = Driver::tokenize(&sc, "x1 42\nSum_3 007").unwrap();
+        assert_eq!(
+            ids(&tokens),
+            vec!["ident", "IGNORE", "number", "IGNORE", "ident", "IGNORE", "number"]
+        );
+        assert_eq!(tokens[4].value, "x53x75x6Dx5Fx33");
+        assert_eq!((tokens[6].line, tokens[6].column), (2, 7));
+    }
+
+    #[test]
+    fn render_with_partial_matches() {
+        // whack starts on \oo but never gets to its closing \
+        let files = [
+            ("wiki/whackamole.tt", "whack", 0),
+            ("wiki/noto.tt", "pqrs", 0),
+        ];
+        let sc = scanner(&files, MatchPolicy::MaximalMunch);
+        let source = "pq\\oo";
+        let e = Driver::tokenize(&sc, source).unwrap_err();
+        assert_eq!(e.partial, vec![("whack".to_string(), 3)]);
+        assert_eq!(
+            e.render("source.txt", source, sc.newline),
+            "error: no token matches at x5C\n --> source.txt:1:3\n  |\n1 | pq\\oo\n  |   ^\n  = note: whack took 3 chars before running into E\n"
+        );
+    }
+
+    #[test]
+    fn render_the_line_by_the_scanner_newline() {
+        let (alpha, newline) = Scanner::alphabet_build("x20x0ax5C x6fpqrx73").unwrap();
+        let trans = vec![
+            TransitionTable::new(DFA::from_file("wiki/noto.tt").unwrap(), "pqrs".into(), None),
+            TransitionTable::new(
+                DFA::from_file("wiki/anyone.tt").unwrap(),
+                "IGNORE".into(),
+                None,
+            ),
+        ];
+        let sc = Scanner::new(newline, alpha, trans);
+        let source = "pq pqz";
+        let e = Driver::tokenize(&sc, source).unwrap_err();
+        assert_eq!((e.line, e.column), (2, 3));
+        assert_eq!(
+            e.render("source.txt", source, sc.newline),
+            "error: x7A is not in the alphabet\n --> source.txt:2:3\n  |\n2 | pqz\n  |   ^\n"
+        );
+    }
+
+    #[test]
+    fn policy_names() {
+        assert_eq!("maximal-munch".parse(), Ok(MatchPolicy::MaximalMunch));
+        assert_eq!("first-defined".parse(), Ok(MatchPolicy::FirstDefined));
+        assert_eq!("shortest".parse(), Ok(MatchPolicy::Shortest));
+        assert!("longest".parse::<MatchPolicy>().is_err());
+        assert_eq!(MatchPolicy::FirstDefined.to_string(), "first-defined");
+    }
+
+    #[test]
+    fn trace_wiki() {
+        let sc = Scanner::from_file("wiki/scan.u").unwrap();
+        let source = std::fs::read_to_string("wiki/source.txt").unwrap();
+        let (tokens, trace) = traced(&sc, &source);
+        let tokens = tokens.unwrap();
+        assert_eq!(tokens, Driver::tokenize(&sc, &source).unwrap());
+
+        let lines: Vec<&str> = trace.lines().collect();
+        assert_eq!(
+            lines[0],
+            "1:1 pqrs wins, longest match of 18, tied with opqr, wins on being listed first"
+        );
+        assert_eq!(lines[1], "  pqrs: match 18, dies on char 19");
+        assert_eq!(lines[7], "1:19 IGNORE wins, longest match of 1");
+        // a heading and a line per table for each token
+        assert_eq!(lines.len(), tokens.len() * 7);
+    }
+
+    #[test]
+    fn trace_reasons() {
+        let files = [("wiki/nots.tt", "opqr", 0), ("wiki/noto.tt", "pqrs", 1)];
+        let sc = scanner(&files, MatchPolicy::FirstDefined);
+        let (_, trace) = traced(&sc, "pqo");
+        assert_eq!(
+            trace.lines().next(),
+            Some("1:1 pqrs wins, first defined match of 2, also matched by opqr, wins on higher priority")
+        );
+
+        let files = [("wiki/noto.tt", "pqrs", 0)];
+        let sc = scanner(&files, MatchPolicy::MaximalMunch);
+        let (_, trace) = traced(&sc, "pp");
+        assert_eq!(
+            trace,
+            "1:1 pqrs wins, longest match of 2\n  pqrs: match 2, reaches the end\n"
+        );
+        let (_, trace) = traced(&sc, "o");
+        assert_eq!(
+            trace,
+            "1:1 no table matches\n  pqrs: match 0, dies on char 1\n"
+        );
+    }
+
+    #[test]
+    fn trace_counts_chars() {
+        let sc = Scanner::from_file("tests/other.u").unwrap();
+        let (_, trace) = traced(&sc, "\\ üü\nq");
+        let lines: Vec<&str> = trace.lines().collect();
+        assert_eq!(lines[0], "1:1 comment wins, longest match of 4");
+        assert_eq!(lines[2], "  comment: match 4, dies on char 5");
+    }
+}
@@ -0,0 +1,301 @@
+use crate::dfa::DFA;
+use std::path::PathBuf;
+
+// Expressions combine .tt files wherever a scanner definition names one.
+// They can't contain whitespace, since the definition splits on it.
+// A file name with operator chars in it goes in double quotes: "a|b.tt"
+// wiki/noto.tt|wiki/nots.tt      union
+// wiki/noto.tt&wiki/nots.tt      intersection
+// wiki/noto.tt&!tests/pq.tt      difference
+// !wiki/anyone.tt                complement
+// wiki/noto.tt(wiki/endsq.tt)     concatenation, by writing one after the other
+// wiki/twosmallwords.tt+          one or more, * is zero or more, ? is optional
+// ~wiki/endsq.tt                 reversal
+// (wiki/noto.tt|wiki/nots.tt)&!tests/pq.tt
+// * + ? bind tightest, then ! ~, then concatenation, then &, then |
+
+const OPERATORS: &[char] = &['|', '&', '!', '~', '*', '+', '?', '(', ')'];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    File(PathBuf),
+    Union(Box<Expr>, Box<Expr>),
+    Intersection(Box<Expr>, Box<Expr>),
+    Complement(Box<Expr>),
+    Concat(Box<Expr>, Box<Expr>),
+    Star(Box<Expr>),
+    Plus(Box<Expr>),
+    Optional(Box<Expr>),
+    Reverse(Box<Expr>),
+}
+
+impl Expr {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let chars: Vec<char> = input.chars().collect();
+        let mut pos = 0;
+        let expr = Expr::parse_union(&chars, &mut pos)?;
+        if pos < chars.len() {
+            return Err(format!("unexpected {:?} in {}", chars[pos], input));
+        }
+        Ok(expr)
+    }
+
+    fn parse_union(chars: &[char], pos: &mut usize) -> Result<Self, String> {
+        let mut expr = Expr::parse_intersection(chars, pos)?;
+        while chars.get(*pos) == Some(&'|') {
+            *pos += 1;
+            let right = Expr::parse_intersection(chars, pos)?;
+            expr = Expr::Union(Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_intersection(chars: &[char], pos: &mut usize) -> Result<Self, String> {
+        let mut expr = Expr::parse_concat(chars, pos)?;
+        while chars.get(*pos) == Some(&'&') {
+            *pos += 1;
+            let right = Expr::parse_concat(chars, pos)?;
+            expr = Expr::Intersection(Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_concat(chars: &[char], pos: &mut usize) -> Result<Self, String> {
+        let mut expr = Expr::parse_unary(chars, pos)?;
+        while *pos < chars.len() && !"|&)*+?".contains(chars[*pos]) {
+            let right = Expr::parse_unary(chars, pos)?;
+            expr = Expr::Concat(Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(chars: &[char], pos: &mut usize) -> Result<Self, String> {
+        match chars.get(*pos) {
+            Some('!') => {
+                *pos += 1;
+                Ok(Expr::Complement(Box::new(Expr::parse_unary(chars, pos)?)))
+            }
+            Some('~') => {
+                *pos += 1;
+                Ok(Expr::Reverse(Box::new(Expr::parse_unary(chars, pos)?)))
+            }
+            _ => Expr::parse_postfix(chars, pos),
+        }
+    }
+
+    fn parse_postfix(chars: &[char], pos: &mut usize) -> Result<Self, String> {
+        let mut expr = Expr::parse_primary(chars, pos)?;
+        loop {
+            expr = match chars.get(*pos) {
+                Some('*') => Expr::Star(Box::new(expr)),
+                Some('+') => Expr::Plus(Box::new(expr)),
+                Some('?') => Expr::Optional(Box::new(expr)),
+                _ => return Ok(expr),
+            };
+            *pos += 1;
+        }
+    }
+
+    fn parse_primary(chars: &[char], pos: &mut usize) -> Result<Self, String> {
+        match chars.get(*pos) {
+            Some('(') => {
+                *pos += 1;
+                let expr = Expr::parse_union(chars, pos)?;
+                if chars.get(*pos) != Some(&')') {
+                    return Err("missing )".to_string());
+                }
+                *pos += 1;
+                Ok(expr)
+            }
+            Some('"') => {
+                let start = *pos + 1;
+                let end = chars[start..]
+                    .iter()
+                    .position(|&c| c == '"')
+                    .ok_or_else(|| "missing closing \"".to_string())?;
+                *pos = start + end + 1;
+                if end == 0 {
+                    return Err("missing file name".to_string());
+                }
+                let file_name: String = chars[start..start + end].iter().collect();
+                Ok(Expr::File(PathBuf::from(file_name)))
+            }
+            _ => {
+                let start = *pos;
+                while *pos < chars.len() && !OPERATORS.contains(&chars[*pos]) && chars[*pos] != '"'
+                {
+                    *pos += 1;
+                }
+                if start == *pos {
+                    return Err("missing file name".to_string());
+                }
+                let file_name: String = chars[start..*pos].iter().collect();
+                Ok(Expr::File(PathBuf::from(file_name)))
+            }
+        }
+    }
+
+    /// Parses and evaluates an expression in one go
+    pub fn load(input: &str, columns: usize) -> Result<DFA, Box<dyn std::error::Error>> {
+        Expr::parse(input)?.eval(columns)
+    }
+
+    /// Loads the files and combines them. Complements are taken over the
+    /// alphabet's columns, or the table's own if it is wider.
+    pub fn eval(&self, columns: usize) -> Result<DFA, Box<dyn std::error::Error>> {
+        Ok(match self {
+            Expr::File(path) => DFA::from_file(path)?,
+            Expr::Union(a, b) => a.eval(columns)?.union(&b.eval(columns)?),
+            Expr::Intersection(a, b) => match b.as_ref() {
+                Expr::Complement(b) => a.eval(columns)?.difference(&b.eval(columns)?),
+                _ => a.eval(columns)?.intersection(&b.eval(columns)?),
+            },
+            Expr::Complement(a) => a.eval(columns)?.complement(columns),
+            Expr::Concat(a, b) => a.eval(columns)?.concat(&b.eval(columns)?),
+            Expr::Star(a) => a.eval(columns)?.star(),
+            Expr::Plus(a) => a.eval(columns)?.plus(),
+            Expr::Optional(a) => a.eval(columns)?.optional(),
+            Expr::Reverse(a) => a.eval(columns)?.reverse(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn file(name: &str) -> Box<Expr> {
+        Box::new(Expr::File(PathBuf::from(name)))
+    }
+
+    #[test]
+    fn plain_file_name() {
+        assert_eq!(Expr::parse("wiki/noto.tt"), Ok(*file("wiki/noto.tt")));
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            Expr::parse("a.tt|b.tt&!c.tt"),
+            Ok(Expr::Union(
+                file("a.tt"),
+                Box::new(Expr::Intersection(
+                    file("b.tt"),
+                    Box::new(Expr::Complement(file("c.tt")))
+                ))
+            ))
+        );
+        assert_eq!(
+            Expr::parse("(a.tt|b.tt)&c.tt"),
+            Ok(Expr::Intersection(
+                Box::new(Expr::Union(file("a.tt"), file("b.tt"))),
+                file("c.tt")
+            ))
+        );
+    }
+
+    #[test]
+    fn composition_precedence() {
+        assert_eq!(
+            Expr::parse("a.tt(b.tt)+|~c.tt*"),
+            Ok(Expr::Union(
+                Box::new(Expr::Concat(
+                    file("a.tt"),
+                    Box::new(Expr::Plus(file("b.tt")))
+                )),
+                Box::new(Expr::Reverse(Box::new(Expr::Star(file("c.tt")))))
+            ))
+        );
+        assert_eq!(
+            Expr::parse("(a.tt)(b.tt)?&c.tt"),
+            Ok(Expr::Intersection(
+                Box::new(Expr::Concat(
+                    file("a.tt"),
+                    Box::new(Expr::Optional(file("b.tt")))
+                )),
+                file("c.tt")
+            ))
+        );
+    }
+
+    #[test]
+    fn quoted_file_names() {
+        assert_eq!(Expr::parse("\"a|b&!c.tt\""), Ok(*file("a|b&!c.tt")));
+        assert_eq!(
+            Expr::parse("!\"(a).tt\"|b.tt"),
+            Ok(Expr::Union(
+                Box::new(Expr::Complement(file("(a).tt"))),
+                file("b.tt")
+            ))
+        );
+        assert!(Expr::parse("\"a.tt").is_err());
+        assert!(Expr::parse("\"\"").is_err());
+    }
+
+    #[test]
+    fn bad_expressions() {
+        assert!(Expr::parse("").is_err());
+        assert!(Expr::parse("a.tt|").is_err());
+        assert!(Expr::parse("(a.tt").is_err());
+        assert!(Expr::parse("a.tt)").is_err());
+        assert!(Expr::parse("!").is_err());
+        assert!(Expr::parse("*a.tt").is_err());
+        assert!(Expr::parse("a.tt|+").is_err());
+    }
+
+    #[test]
+    fn evaluate() {
+        let plain = Expr::parse("wiki/noto.tt").unwrap().eval(0).unwrap();
+        assert_eq!(plain, DFA::from_file("wiki/noto.tt").unwrap());
+
+        let rest = Expr::parse("wiki/noto.tt&!tests/pq.tt")
+            .unwrap()
+            .eval(0)
+            .unwrap();
+        let noto = DFA::from_file("wiki/noto.tt").unwrap();
+        let pq = DFA::from_file("tests/pq.tt").unwrap();
+        assert_eq!(rest.equivalent(&noto.difference(&pq)), Ok(()));
+
+        let either = Expr::parse("!!wiki/noto.tt|wiki/nots.tt")
+            .unwrap()
+            .eval(0)
+            .unwrap();
+        let nots = DFA::from_file("wiki/nots.tt").unwrap();
+        assert_eq!(either.equivalent(&noto.union(&nots)), Ok(()));
+
+        let words = Expr::parse("wiki/twosmallwords.tt+")
+            .unwrap()
+            .eval(0)
+            .unwrap();
+        let twosmallwords = DFA::from_file("wiki/twosmallwords.tt").unwrap();
+        assert_eq!(words.equivalent(&twosmallwords.plus()), Ok(()));
+
+        let noto_then_endsq = Expr::parse("~(wiki/noto.tt(wiki/endsq.tt))")
+            .unwrap()
+            .eval(0)
+            .unwrap();
+        let endsq = DFA::from_file("wiki/endsq.tt").unwrap();
+        assert_eq!(
+            noto_then_endsq.equivalent(&noto.concat(&endsq).reverse()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn complement_over_the_alphabet() {
+        let noto = DFA::from_file("wiki/noto.tt").unwrap();
+        let wider = noto.columns() + 1;
+        let not_noto = Expr::load("!wiki/noto.tt", wider).unwrap();
+        assert_eq!(not_noto.columns(), wider);
+        assert!(not_noto.accepts(&[wider - 1]));
+        assert!(!Expr::load("!wiki/noto.tt", 0)
+            .unwrap()
+            .accepts(&[wider - 1]));
+    }
+
+    #[test]
+    fn missing_file() {
+        let expr = Expr::parse("wiki/noto.tt|this_file_does_not_exist.tt").unwrap();
+        assert!(expr.eval(0).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use crate::driver::{Driver, MatchPolicy, Token};
+use crate::random::Random;
+use crate::scanner::Scanner;
+
+/// How many texts are tried for each token before giving up on the sample
+const ATTEMPTS: usize = 100;
+
+/// A random source text and the tokens the scanner should split it into
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    pub source: String,
+    pub tokens: Vec<Token>,
+}
+
+/// Generates source texts by gluing together random texts of the scanner's
+/// transition tables, so the tokens are known from how the text was put
+/// together. A text is only kept if every piece still stands alone with it
+/// added: no other table ties with it and no match runs past its end, or
+/// for the shortest policy stops short of it. When a text would merge with
+/// the one before, a random text of some table is tried in between.
+pub struct Fuzzer<'s> {
+    scanner: &'s Scanner,
+    symbols: Vec<Option<char>>,
+    lengths: Vec<Vec<usize>>,
+}
+
+impl<'s> Fuzzer<'s> {
+    /// Tokens are at most max_length chars long
+    pub fn new(scanner: &'s Scanner, max_length: usize) -> Self {
+        let trans = scanner.get_trans();
+        let columns = trans.iter().map(|t| t.tt.columns()).max().unwrap_or(0);
+        let mut symbols = vec![None; columns];
+        let alpha = scanner.get_alpha();
+        for (&letter, &column) in alpha.iter() {
+            if column < columns && symbols[column].is_none() {
+                symbols[column] = Some(letter);
+            }
+        }
+        if let Some(other) = alpha.other().filter(|&other| other < columns) {
+            symbols[other] = alpha.other_letter();
+        }
+        let lengths = trans
+            .iter()
+            .map(|t| {
+                (1..=max_length)
+                    .filter(|&length| t.tt.count_of_length(length) > 0)
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            scanner,
+            symbols,
+            lengths,
+        }
+    }
+
+    /// A sample of up to the given number of tokens, counting separators. It
+    /// is shorter when no table has a text that can follow the ones picked
+    /// so far.
+    pub fn generate(&self, tokens: usize, random: &mut Random) -> Sample {
+        let usable: Vec<usize> = (0..self.lengths.len())
+            .filter(|&t| !self.lengths[t].is_empty())
+            .collect();
+        let mut word = vec![];
+        let mut pieces = vec![];
+
+        while !usable.is_empty() && pieces.len() < tokens {
+            let mut added = false;
+            for _ in 0..ATTEMPTS {
+                let piece = match self.piece(&usable, random) {
+                    Some(piece) => piece,
+                    None => continue,
+                };
+                if self.try_add(&mut word, &mut pieces, &[&piece]) {
+                    added = true;
+                    break;
+                }
+                let separator = match self.piece(&usable, random) {
+                    Some(separator) => separator,
+                    None => continue,
+                };
+                if pieces.len() + 2 <= tokens
+                    && self.try_add(&mut word, &mut pieces, &[&separator, &piece])
+                {
+                    added = true;
+                    break;
+                }
+            }
+            if !added {
+                break;
+            }
+        }
+
+        self.to_sample(&word, &pieces)
+    }
+
+    /// A random text of a random table, as (table, text)
+    fn piece(&self, usable: &[usize], random: &mut Random) -> Option<(usize, Vec<usize>)> {
+        let table = usable[random.below(usable.len() as u128) as usize];
+        let lengths = &self.lengths[table];
+        let length = lengths[random.below(lengths.len() as u128) as usize];
+        let text = self.scanner.get_trans()[table].tt.sample(length, random)?;
+        if text.iter().any(|&c| self.symbols[c].is_none()) {
+            return None;
+        }
+        Some((table, text))
+    }
+
+    /// Adds the pieces to the end of the word if every piece stands alone
+    /// afterwards, otherwise leaves the word as it was
+    fn try_add(
+        &self,
+        word: &mut Vec<usize>,
+        pieces: &mut Vec<(usize, usize)>,
+        added: &[&(usize, Vec<usize>)],
+    ) -> bool {
+        let (old_word, old_pieces) = (word.len(), pieces.len());
+        for (table, text) in added {
+            word.extend(text);
+            pieces.push((*table, text.len()));
+        }
+
+        let mut start = 0;
+        let fits = pieces.iter().all(|&piece| {
+            let alone = self.stands_alone(&word[start..], piece);
+            start += piece.1;
+            alone
+        });
+        if !fits {
+            word.truncate(old_word);
+            pieces.truncate(old_pieces);
+        }
+        fits
+    }
+
+    /// Is the (table, length) piece the one token the word can start with,
+    /// whatever the order and priorities of tables that tie with it
+    fn stands_alone(&self, word: &[usize], piece: (usize, usize)) -> bool {
+        let trans = self.scanner.get_trans();
+        // Every (table, length) match at the start of the word
+        let mut matches = vec![];
+        for (table, t) in trans.iter().enumerate() {
+            let mut state = if t.tt.states() > 0 { Some(0) } else { None };
+            for (length, &letter) in word.iter().enumerate() {
+                state = state.and_then(|s| t.tt.next(s, letter));
+                match state {
+                    Some(s) if t.tt.is_accepting(s) => matches.push((table, length + 1)),
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+        }
+
+        let contenders: Vec<(usize, usize)> = match self.scanner.policy {
+            MatchPolicy::MaximalMunch => {
+                let longest = matches.iter().map(|m| m.1).max();
+                matches.retain(|m| Some(m.1) == longest);
+                matches
+            }
+            MatchPolicy::Shortest => {
+                let shortest = matches.iter().map(|m| m.1).min();
+                matches.retain(|m| Some(m.1) == shortest);
+                matches
+            }
+            // Any table that matches at all is a contender, and the policy
+            // is defined by their rank
+            MatchPolicy::FirstDefined => {
+                let first = matches
+                    .iter()
+                    .map(|m| m.0)
+                    .max_by_key(|&t| Driver::rank(trans, t));
+                matches.retain(|m| Some(m.0) == first);
+                matches.split_off(matches.len().saturating_sub(1))
+            }
+        };
+        contenders == [piece]
+    }
+
+    fn to_sample(&self, word: &[usize], pieces: &[(usize, usize)]) -> Sample {
+        let trans = self.scanner.get_trans();
+        let source: String = word.iter().filter_map(|&c| self.symbols[c]).collect();
+        let mut tokens = vec![];
+        let mut chars = source.chars();
+        let mut line = 1;
+        let mut column = 1;
+        for &(table, length) in pieces {
+            let lexeme: String = chars.by_ref().take(length).collect();
+            tokens.push(Driver::token(&trans[table], &lexeme, line, column));
+            for letter in lexeme.chars() {
+                if letter == self.scanner.newline {
+                    line += 1;
+                    column = 1;
+                } else {
+                    column += 1;
+                }
+            }
+        }
+
+        Sample { source, tokens }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn driver_agrees(file: &str) {
+        let scanner = Scanner::from_file(file).unwrap();
+        let fuzzer = Fuzzer::new(&scanner, 8);
+        for seed in 0..20 {
+            let sample = fuzzer.generate(30, &mut Random::new(seed));
+            assert!(!sample.tokens.is_empty());
+            assert_eq!(
+                Driver::tokenize(&scanner, &sample.source).unwrap(),
+                sample.tokens
+            );
+        }
+    }
+
+    #[test]
+    fn wiki_scanner() {
+        driver_agrees("wiki/scan.u");
+    }
+
+    #[test]
+    fn other_column() {
+        driver_agrees("tests/other.u");
+    }
+
+    #[test]
+    fn keywords_and_priorities() {
+        driver_agrees("tests/keywords.u");
+        driver_agrees("tests/priorities.u");
+    }
+
+    #[test]
+    fn same_seed_same_sample() {
+        let scanner = Scanner::from_file("wiki/scan.u").unwrap();
+        let fuzzer = Fuzzer::new(&scanner, 8);
+        let a = fuzzer.generate(10, &mut Random::new(36));
+        assert_eq!(a, fuzzer.generate(10, &mut Random::new(36)));
+        assert_eq!(a.tokens.len(), 10);
+    }
+
+    #[test]
+    fn merging_texts_are_rejected() {
+        let scanner = Scanner::from_file("wiki/scan.u").unwrap();
+        let fuzzer = Fuzzer::new(&scanner, 8);
+        let (mut word, mut pieces) = (vec![], vec![]);
+        // ss is only pqrs, but ss then s is one longer pqrs
+        assert!(fuzzer.try_add(&mut word, &mut pieces, &[&(0, vec![7, 7])]));
+        assert!(!fuzzer.try_add(&mut word, &mut pieces, &[&(0, vec![7])]));
+        assert_eq!(pieces, vec![(0, 2)]);
+        // a newline in between keeps them apart
+        assert!(fuzzer.try_add(&mut word, &mut pieces, &[&(5, vec![0]), &(0, vec![7, 7])]));
+        assert_eq!(pieces, vec![(0, 2), (5, 1), (0, 2)]);
+        // p is also opqr and IGNORE, so it can't stand alone
+        assert!(!fuzzer.try_add(&mut word, &mut pieces, &[&(5, vec![0]), &(0, vec![4])]));
+    }
+}
@@ -0,0 +1,208 @@
+use crate::alphabet_translator::alphabet_translator;
+use crate::driver::{Driver, Token};
+use crate::scanner::Scanner;
+use std::path::{Path, PathBuf};
+
+// A golden test is a scanner definition in a directory, with a source to
+// scan and the tokens expected from it. For scan.u they are scan.txt and
+// scan.dat, or else source.txt and tokens.dat as in wiki/.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    pub definition: PathBuf,
+    pub source: PathBuf,
+    pub expected: PathBuf,
+}
+
+/// One line of a token diff, the index is into the expected or actual tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Missing(usize, Token),
+    Extra(usize, Token),
+}
+
+impl Case {
+    /// The cases for every .u file in a directory, in name order. Definitions
+    /// without a source are left out, expected tokens don't have to exist yet.
+    /// Only one definition may use source.txt, or they would all be blessed
+    /// into the same tokens.dat.
+    pub fn discover(dir: impl AsRef<Path>) -> Result<Vec<Case>, Box<dyn std::error::Error>> {
+        let dir = dir.as_ref();
+        let mut definitions: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<_, _>>()?;
+        definitions.retain(|path| path.extension().is_some_and(|e| e == "u"));
+        definitions.sort();
+
+        let shared: Vec<&PathBuf> = definitions
+            .iter()
+            .filter(|d| !d.with_extension("txt").is_file())
+            .collect();
+        if shared.len() > 1 && dir.join("source.txt").is_file() {
+            return Err(format!(
+                "{} and {} would share source.txt, give each one its own stem.txt",
+                shared[0].display(),
+                shared[1].display()
+            )
+            .into());
+        }
+
+        Ok(definitions
+            .into_iter()
+            .filter_map(|definition| {
+                let source = vec![definition.with_extension("txt"), dir.join("source.txt")]
+                    .into_iter()
+                    .find(|path| path.is_file())?;
+                let expected = if source == dir.join("source.txt") {
+                    dir.join("tokens.dat")
+                } else {
+                    definition.with_extension("dat")
+                };
+                Some(Case {
+                    definition,
+                    source,
+                    expected,
+                })
+            })
+            .collect())
+    }
+
+    /// The tokens the driver gets from the source
+    pub fn run(&self) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+        let scanner = Scanner::load(&self.definition)?;
+        let source = std::fs::read_to_string(&self.source)?;
+        Ok(Driver::tokenize(&scanner, &source)?)
+    }
+
+    /// The expected tokens, none if the file doesn't exist yet
+    pub fn expected(&self) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+        if !self.expected.exists() {
+            return Ok(vec![]);
+        }
+        let text = std::fs::read_to_string(&self.expected)?;
+        Ok(text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// Replaces the expected tokens with the given ones
+    pub fn bless(&self, tokens: &[Token]) -> std::io::Result<()> {
+        let lines: String = tokens.iter().map(|t| format!("{}\n", t)).collect();
+        std::fs::write(&self.expected, lines)
+    }
+}
+
+/// Do two tokens say the same thing. Values are compared after decoding,
+/// since expected files may spell out plain chars instead of xHH.
+pub fn same_token(expected: &Token, actual: &Token) -> bool {
+    expected.id == actual.id
+        && expected.line == actual.line
+        && expected.column == actual.column
+        && alphabet_translator::hex_a_string_to_string(&expected.value)
+            == alphabet_translator::hex_a_string_to_string(&actual.value)
+}
+
+/// The tokens to take out of expected and put in to get actual, by longest
+/// common subsequence, in order
+pub fn diff(expected: &[Token], actual: &[Token]) -> Vec<Change> {
+    let (n, m) = (expected.len(), actual.len());
+    // common[i][j] is the longest common subsequence of expected[i..] and actual[j..]
+    let mut common = vec![vec![0; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[i][j] = if same_token(&expected[i], &actual[j]) {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && same_token(&expected[i], &actual[j]) {
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && common[i + 1][j] >= common[i][j + 1]) {
+            changes.push(Change::Missing(i, expected[i].clone()));
+            i += 1;
+        } else {
+            changes.push(Change::Extra(j, actual[j].clone()));
+            j += 1;
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn token(id: &str, value: &str, line: usize, column: usize) -> Token {
+        Token::new(id.to_string(), value.to_string(), line, column)
+    }
+
+    #[test]
+    fn discover_wiki() {
+        let cases = Case::discover("wiki").unwrap();
+        assert_eq!(
+            cases,
+            vec![Case {
+                definition: PathBuf::from("wiki/scan.u"),
+                source: PathBuf::from("wiki/source.txt"),
+                expected: PathBuf::from("wiki/tokens.dat"),
+            }]
+        );
+        // tests/ has definitions but no sources
+        assert_eq!(Case::discover("tests").unwrap(), vec![]);
+        assert!(Case::discover("this_dir_does_not_exist").is_err());
+        assert_eq!(
+            Case::discover("tests/shared_source")
+                .unwrap_err()
+                .to_string(),
+            "tests/shared_source/a.u and tests/shared_source/b.u would share source.txt, \
+             give each one its own stem.txt"
+        );
+    }
+
+    #[test]
+    fn wiki_tokens_match() {
+        let case = &Case::discover("wiki").unwrap()[0];
+        assert_eq!(
+            diff(&case.expected().unwrap(), &case.run().unwrap()),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn readable_values_are_the_same() {
+        let plain = token("pqrs", "pqr", 1, 1);
+        let hex = token("pqrs", "x70x71x72", 1, 1);
+        assert!(same_token(&plain, &hex));
+        assert!(!same_token(&plain, &token("pqrs", "pqr", 1, 2)));
+        assert!(!same_token(&plain, &token("opqr", "pqr", 1, 1)));
+    }
+
+    #[test]
+    fn diff_lines_up_tokens() {
+        let a = token("a", "o", 1, 1);
+        let b = token("b", "p", 1, 2);
+        let c = token("c", "q", 1, 3);
+        let d = token("d", "r", 1, 3);
+        assert_eq!(
+            diff(&[a.clone(), b.clone(), c.clone()], &[a.clone(), c.clone()]),
+            vec![Change::Missing(1, b.clone())]
+        );
+        assert_eq!(
+            diff(&[a.clone(), c.clone()], &[a.clone(), d.clone()]),
+            vec![Change::Missing(1, c), Change::Extra(1, d)]
+        );
+        assert_eq!(
+            diff(&[], std::slice::from_ref(&b)),
+            vec![Change::Extra(0, b)]
+        );
+    }
+}
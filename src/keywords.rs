@@ -0,0 +1,90 @@
+use crate::alphabet_translator::alphabet_translator;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// A keyword table attached to a transition table in the scanner definition.
+/// Each line of the file looks like
+/// if        IF
+/// x5cbegin  BEGIN
+/// lexeme is the exact text of the keyword, using xHH for special chars
+/// id is the token id that replaces the table's own id on an exact match
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct KeywordTable {
+    keywords: BTreeMap<String, String>,
+}
+
+impl KeywordTable {
+    pub fn new(keywords: BTreeMap<String, String>) -> Self {
+        Self { keywords }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+
+        let mut keywords = BTreeMap::new();
+        for line in reader.lines() {
+            let line = line?;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+
+            match tokens.as_slice() {
+                [] => continue,
+                [lexeme, id] => {
+                    keywords.insert(
+                        alphabet_translator::hex_a_string_to_string(lexeme),
+                        id.to_string(),
+                    );
+                }
+                _ => return Err(format!("invalid keyword entry: {}", line).into()),
+            }
+        }
+
+        Ok(KeywordTable::new(keywords))
+    }
+
+    /// Returns the keyword token id for a lexeme, if the lexeme is a keyword.
+    pub fn reclassify(&self, lexeme: &str) -> Option<&str> {
+        self.keywords.get(lexeme).map(String::as_str)
+    }
+
+    /// The keywords and their ids, in lexeme order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.keywords
+            .iter()
+            .map(|(lexeme, id)| (lexeme.as_str(), id.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn load_keyword_file() {
+        let kw = KeywordTable::from_file("tests/keywords.kw").unwrap();
+        assert_eq!(kw.reclassify("pop"), Some("POP"));
+        assert_eq!(kw.reclassify("pqrs"), Some("PQRS"));
+        assert_eq!(kw.reclassify("s s"), Some("SPACED"));
+        assert_eq!(kw.reclassify("po"), None);
+    }
+
+    #[test]
+    fn empty_keyword_file() {
+        let kw = KeywordTable::from_file("tests/empty_file.tt").unwrap();
+        assert_eq!(kw, KeywordTable::default());
+    }
+
+    #[test]
+    #[should_panic]
+    fn bad_keyword_file() {
+        KeywordTable::from_file("tests/two_liner.tt").unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn missing_keyword_file() {
+        KeywordTable::from_file("this_file_does_not_exist.kw").unwrap();
+    }
+}
@@ -1,5 +1,16 @@
+pub mod alphabet;
 pub mod alphabet_translator;
+pub mod analysis;
+pub mod coverage;
 pub mod dfa;
 pub mod driver;
+pub mod expr;
+pub mod fuzz;
+pub mod golden;
+pub mod keywords;
+pub mod nfa;
+pub mod product;
+pub mod random;
 pub mod regex;
+pub mod repl;
 pub mod scanner;
@@ -0,0 +1,215 @@
+use crate::dfa::DFA;
+use std::collections::{BTreeSet, HashMap};
+
+/// A nondeterministic automaton, only used on the way from one DFA to
+/// another when composing them. None as an edge letter is an epsilon edge.
+#[derive(Debug, Default, Clone)]
+pub struct NFA {
+    starts: Vec<usize>,
+    accepting: Vec<bool>,
+    edges: Vec<Vec<(Option<usize>, usize)>>,
+    columns: usize,
+}
+
+impl NFA {
+    pub fn from_dfa(dfa: &DFA) -> Self {
+        let columns = dfa.columns();
+        let edges = (0..dfa.states())
+            .map(|row| {
+                (0..columns)
+                    .filter_map(|letter| dfa.next(row, letter).map(|next| (Some(letter), next)))
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            starts: if dfa.states() > 0 { vec![0] } else { vec![] },
+            accepting: (0..dfa.states()).map(|row| dfa.is_accepting(row)).collect(),
+            edges,
+            columns,
+        }
+    }
+
+    fn add_state(&mut self, accepting: bool) -> usize {
+        self.accepting.push(accepting);
+        self.edges.push(vec![]);
+        self.accepting.len() - 1
+    }
+
+    /// Copies the states of the other NFA in after this one's, returning the
+    /// offset their numbers were shifted by
+    fn append(&mut self, other: &NFA) -> usize {
+        let offset = self.accepting.len();
+        self.accepting.extend(other.accepting.iter().copied());
+        self.edges.extend(other.edges.iter().map(|edges| {
+            edges
+                .iter()
+                .map(|&(letter, next)| (letter, next + offset))
+                .collect::<Vec<_>>()
+        }));
+        self.columns = self.columns.max(other.columns);
+        offset
+    }
+
+    fn accepting_states(&self) -> Vec<usize> {
+        (0..self.accepting.len())
+            .filter(|&s| self.accepting[s])
+            .collect()
+    }
+
+    /// Accepts a text of this NFA followed by a text of the other
+    pub fn concat(mut self, other: &NFA) -> Self {
+        let offset = self.append(other);
+        for end in self.accepting_states() {
+            if end < offset {
+                self.accepting[end] = false;
+                for &start in other.starts.iter() {
+                    self.edges[end].push((None, start + offset));
+                }
+            }
+        }
+        self
+    }
+
+    /// Accepts one or more texts of this NFA in a row
+    pub fn plus(mut self) -> Self {
+        for end in self.accepting_states() {
+            for &start in self.starts.clone().iter() {
+                self.edges[end].push((None, start));
+            }
+        }
+        self
+    }
+
+    /// Accepts the empty text, or a text of this NFA
+    pub fn optional(mut self) -> Self {
+        let start = self.add_state(true);
+        for &old in self.starts.clone().iter() {
+            self.edges[start].push((None, old));
+        }
+        self.starts = vec![start];
+        self
+    }
+
+    /// Accepts zero or more texts of this NFA in a row
+    pub fn star(self) -> Self {
+        self.plus().optional()
+    }
+
+    /// Accepts the texts of this NFA spelled backwards
+    pub fn reverse(self) -> Self {
+        let mut edges = vec![vec![]; self.accepting.len()];
+        for (from, out) in self.edges.iter().enumerate() {
+            for &(letter, to) in out.iter() {
+                edges[to].push((letter, from));
+            }
+        }
+        let mut accepting = vec![false; self.accepting.len()];
+        for &start in self.starts.iter() {
+            accepting[start] = true;
+        }
+
+        Self {
+            starts: self.accepting_states(),
+            accepting,
+            edges,
+            columns: self.columns,
+        }
+    }
+
+    fn closure(&self, states: impl IntoIterator<Item = usize>) -> BTreeSet<usize> {
+        let mut closure = BTreeSet::new();
+        let mut stack: Vec<usize> = states.into_iter().collect();
+        while let Some(state) = stack.pop() {
+            if closure.insert(state) {
+                for &(letter, next) in self.edges[state].iter() {
+                    if letter.is_none() {
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        closure
+    }
+
+    /// The subset construction, each row of the DFA is a set of NFA states.
+    /// The empty set becomes `E`.
+    pub fn to_dfa(&self) -> DFA {
+        let start = self.closure(self.starts.iter().copied());
+        let mut rows: HashMap<BTreeSet<usize>, usize> = HashMap::new();
+        rows.insert(start.clone(), 0);
+        let mut order = vec![start];
+        let mut accepting = vec![];
+        let mut transitions = vec![];
+
+        let mut i = 0;
+        while i < order.len() {
+            let set = order[i].clone();
+            accepting.push(set.iter().any(|&s| self.accepting[s]));
+            let row = (0..self.columns)
+                .map(|column| {
+                    let next = self.closure(set.iter().flat_map(|&s| {
+                        self.edges[s]
+                            .iter()
+                            .filter(move |&&(letter, _)| letter == Some(column))
+                            .map(|&(_, next)| next)
+                    }));
+                    if next.is_empty() {
+                        return None;
+                    }
+                    let new_row = rows.len();
+                    Some(*rows.entry(next.clone()).or_insert_with(|| {
+                        order.push(next);
+                        new_row
+                    }))
+                })
+                .collect();
+            transitions.push(row);
+            i += 1;
+        }
+
+        DFA::from_table(accepting, transitions)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let endsq = DFA::from_file("wiki/endsq.tt").unwrap();
+        assert_eq!(NFA::from_dfa(&endsq).to_dfa().equivalent(&endsq), Ok(()));
+    }
+
+    #[test]
+    fn concat_pq_with_itself() {
+        let pq = NFA::from_dfa(&DFA::from_file("tests/pq.tt").unwrap());
+        let dfa = pq.clone().concat(&pq).to_dfa();
+        assert!(dfa.accepts(&[4, 5]) && dfa.accepts(&[4, 5, 4, 5]) && dfa.accepts(&[5, 4, 5]));
+        assert!(!dfa.accepts(&[4]) && !dfa.accepts(&[4, 5, 4, 5, 4]));
+    }
+
+    #[test]
+    fn star_plus_optional() {
+        let anyone = NFA::from_dfa(&DFA::from_file("wiki/anyone.tt").unwrap());
+        let star = anyone.clone().star().to_dfa();
+        let plus = anyone.clone().plus().to_dfa();
+        let optional = anyone.optional().to_dfa();
+        assert!(star.accepts(&[]) && star.accepts(&[1, 2, 3]));
+        assert!(!plus.accepts(&[]) && plus.accepts(&[1, 2, 3]));
+        assert!(optional.accepts(&[]) && optional.accepts(&[1]) && !optional.accepts(&[1, 2]));
+    }
+
+    #[test]
+    fn reverse_endsq() {
+        let endsq = DFA::from_file("wiki/endsq.tt").unwrap();
+        let backwards = NFA::from_dfa(&endsq).reverse().to_dfa();
+        // endsq ends in q, so backwards starts with q
+        assert!(backwards.accepts(&[5, 4]) && !backwards.accepts(&[4, 5]));
+        assert!(endsq.accepts(&[4, 5]) && !endsq.accepts(&[5, 4]));
+        let whack = DFA::from_file("wiki/whackamole.tt").unwrap();
+        let backwards = NFA::from_dfa(&whack).reverse().to_dfa();
+        assert_eq!(backwards.equivalent(&whack), Ok(()));
+    }
+}
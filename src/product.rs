@@ -0,0 +1,194 @@
+use crate::dfa::DFA;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// A tuple of rows, one per DFA in the product. None here represents 'E'
+pub type States = Vec<Option<usize>>;
+
+/// The product automaton of several DFAs run side by side over the same
+/// columns. Only the tuples reachable from the start are ever built, and
+/// tuples where every DFA has died are never explored.
+pub struct Product<'d> {
+    dfas: Vec<&'d DFA>,
+    columns: usize,
+}
+
+impl<'d> Product<'d> {
+    pub fn new(dfas: Vec<&'d DFA>) -> Self {
+        let columns = dfas.iter().map(|d| d.columns()).max().unwrap_or(0);
+        Self { dfas, columns }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn start(&self) -> States {
+        self.dfas
+            .iter()
+            .map(|d| if d.states() > 0 { Some(0) } else { None })
+            .collect()
+    }
+
+    pub fn step(&self, states: &[Option<usize>], letter: usize) -> States {
+        self.dfas
+            .iter()
+            .zip(states)
+            .map(|(d, s)| s.and_then(|s| d.next(s, letter)))
+            .collect()
+    }
+
+    /// Which DFAs accept in the given tuple
+    pub fn accepting(&self, states: &[Option<usize>]) -> Vec<bool> {
+        self.dfas
+            .iter()
+            .zip(states)
+            .map(|(d, s)| s.is_some_and(|s| d.is_accepting(s)))
+            .collect()
+    }
+
+    /// Builds the product as a DFA of its own, where a tuple accepts when
+    /// `accept` holds for the acceptance of each DFA. Tuples where every DFA
+    /// has died become `E`.
+    pub fn build(&self, accept: impl Fn(&[bool]) -> bool) -> DFA {
+        let start = self.start();
+        let mut rows: HashMap<States, usize> = HashMap::new();
+        let mut order = vec![start.clone()];
+        rows.insert(start, 0);
+        let mut accepting = vec![];
+        let mut transitions = vec![];
+
+        let mut i = 0;
+        while i < order.len() {
+            let states = order[i].clone();
+            accepting.push(accept(&self.accepting(&states)));
+            let row = (0..self.columns)
+                .map(|letter| {
+                    let next = self.step(&states, letter);
+                    if next.iter().all(Option::is_none) {
+                        return None;
+                    }
+                    let new_row = rows.len();
+                    Some(*rows.entry(next.clone()).or_insert_with(|| {
+                        order.push(next);
+                        new_row
+                    }))
+                })
+                .collect();
+            transitions.push(row);
+            i += 1;
+        }
+
+        DFA::from_table(accepting, transitions)
+    }
+
+    /// Returns the shortest word of columns, shortlex first, that leads to a
+    /// tuple where `goal` holds for the acceptance of each DFA.
+    pub fn shortest_word(&self, goal: impl Fn(&[bool]) -> bool) -> Option<Vec<usize>> {
+        self.search(vec![(self.start(), vec![])], |states, _| {
+            goal(&self.accepting(states))
+        })
+    }
+
+    /// Like `shortest_word`, but the empty word is never an answer.
+    pub fn shortest_nonempty_word(&self, goal: impl Fn(&[bool]) -> bool) -> Option<Vec<usize>> {
+        self.search(self.firsts(), |states, _| goal(&self.accepting(states)))
+    }
+
+    /// Calls `visit` once for every tuple reachable by a non-empty word, with
+    /// the shortest such word, shortlex first. Tuples where every DFA has
+    /// died are skipped.
+    pub fn visit(&self, mut visit: impl FnMut(&[Option<usize>], &[usize])) {
+        self.search(self.firsts(), |states, word| {
+            visit(states, word);
+            false
+        });
+    }
+
+    /// The tuples one letter from the start
+    fn firsts(&self) -> Vec<(States, Vec<usize>)> {
+        let start = self.start();
+        (0..self.columns)
+            .map(|letter| (self.step(&start, letter), vec![letter]))
+            .collect()
+    }
+
+    /// Breadth first search from the starts, until `goal` holds for a tuple
+    /// and its word
+    fn search(
+        &self,
+        starts: Vec<(States, Vec<usize>)>,
+        mut goal: impl FnMut(&[Option<usize>], &[usize]) -> bool,
+    ) -> Option<Vec<usize>> {
+        let mut seen: HashSet<States> = HashSet::new();
+        let mut queue = VecDeque::new();
+        for (states, word) in starts {
+            if states.iter().all(Option::is_none) || !seen.insert(states.clone()) {
+                continue;
+            }
+            queue.push_back((states, word));
+        }
+
+        while let Some((states, word)) = queue.pop_front() {
+            if goal(&states, &word) {
+                return Some(word);
+            }
+            for letter in 0..self.columns {
+                let next = self.step(&states, letter);
+                if next.iter().all(Option::is_none) || !seen.insert(next.clone()) {
+                    continue;
+                }
+                let mut next_word = word.clone();
+                next_word.push(letter);
+                queue.push_back((next, next_word));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shortest_common_word() {
+        let noto = DFA::from_file("wiki/noto.tt").unwrap();
+        let endsq = DFA::from_file("wiki/endsq.tt").unwrap();
+        let product = Product::new(vec![&noto, &endsq]);
+        // "q" is accepted by both
+        assert_eq!(product.shortest_word(|a| a[0] && a[1]), Some(vec![5]));
+    }
+
+    #[test]
+    fn nonempty_words_only() {
+        let noto = DFA::from_file("wiki/noto.tt").unwrap();
+        let product = Product::new(vec![&noto]);
+        assert_eq!(product.shortest_word(|a| !a[0]), Some(vec![]));
+        // words where every DFA has died are never considered
+        assert_eq!(product.shortest_nonempty_word(|a| !a[0]), None);
+        assert_eq!(product.shortest_nonempty_word(|a| a[0]), Some(vec![4]));
+    }
+
+    #[test]
+    fn build_intersection() {
+        let noto = DFA::from_file("wiki/noto.tt").unwrap();
+        let endsq = DFA::from_file("wiki/endsq.tt").unwrap();
+        let both = Product::new(vec![&noto, &endsq]).build(|a| a[0] && a[1]);
+        assert!(both.accepts(&[4, 5]));
+        assert!(both.accepts(&[5]));
+        assert!(!both.accepts(&[4]));
+        assert!(!both.accepts(&[3, 5]));
+    }
+
+    #[test]
+    fn disjoint_languages() {
+        let noto = DFA::from_file("wiki/noto.tt").unwrap();
+        let anyone = DFA::from_file("wiki/anyone.tt").unwrap();
+        let whack = DFA::from_file("wiki/whackamole.tt").unwrap();
+        let product = Product::new(vec![&noto, &anyone, &whack]);
+        assert_eq!(product.shortest_word(|a| a[0] && a[2]), None);
+        assert_eq!(product.shortest_word(|a| a[1] && a[2]), None);
+        assert_eq!(product.shortest_word(|a| a[0] && a[1]), Some(vec![4]));
+    }
+}
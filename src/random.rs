@@ -0,0 +1,67 @@
+/// A small seeded random number generator (SplitMix64), so generated texts
+/// can be reproduced from the seed alone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn next_u128(&mut self) -> u128 {
+        ((self.next_u64() as u128) << 64) | self.next_u64() as u128
+    }
+
+    /// A uniformly random number in 0..bound, bound must not be 0
+    pub fn below(&mut self, bound: u128) -> u128 {
+        // Reject the top partial range so every value is equally likely
+        let zone = u128::MAX - (u128::MAX % bound + 1) % bound;
+        loop {
+            let n = self.next_u128();
+            if n <= zone {
+                return n % bound;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut a = Random::new(7);
+        let mut b = Random::new(7);
+        let mut c = Random::new(8);
+        let a: Vec<u64> = (0..5).map(|_| a.next_u64()).collect();
+        let b: Vec<u64> = (0..5).map(|_| b.next_u64()).collect();
+        let c: Vec<u64> = (0..5).map(|_| c.next_u64()).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn below_stays_in_range() {
+        let mut random = Random::new(1);
+        let mut seen = [false; 3];
+        for _ in 0..100 {
+            let n = random.below(3);
+            assert!(n < 3);
+            seen[n as usize] = true;
+        }
+        assert_eq!(seen, [true; 3]);
+        assert_eq!(random.below(1), 0);
+        assert!(random.below(u128::MAX) < u128::MAX);
+    }
+}
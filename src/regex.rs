@@ -1,9 +1,9 @@
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::collections::BTreeMap;
 
+use crate::alphabet::Alphabet;
 use crate::dfa::DFA;
 
-type Alphabet = BTreeMap<char, usize>;
 type State = usize;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -14,8 +14,16 @@ pub struct Regex<'d, 'a, 't> {
     pub replace_with: Option<String>,
     state: Cell<Option<usize>>,
     length: Cell<usize>,
+    visits: RefCell<Option<Visits>>,
 }
 
+/// A match as (bytes, newlines, column after it), with its length in chars
+/// and where the DFA ran into E, see `Regex::traced_match`
+pub type Traced = ((usize, usize, usize), usize, Option<usize>);
+
+/// How many times each (state, column) transition was taken
+pub type Visits = BTreeMap<(State, usize), usize>;
+
 impl<'d, 'a, 't> Regex<'d, 'a, 't> {
     pub fn new(
         dfa: &'d DFA,
@@ -30,21 +38,36 @@ impl<'d, 'a, 't> Regex<'d, 'a, 't> {
             replace_with,
             state: Cell::new(Some(0)),
             length: Cell::new(0),
+            visits: RefCell::new(None),
         }
     }
 
+    /// Starts counting the transitions taken from now on
+    pub fn record(&self) {
+        self.visits.replace(Some(Visits::new()));
+    }
+
+    /// The transitions taken since `record`, and stops counting
+    pub fn take_visits(&self) -> Visits {
+        self.visits.take().unwrap_or_default()
+    }
+
     // takes in a letter and returns what state we end up at
     fn accept(&self, letter: char) -> Option<State> {
         if let Some(current_state) = self.state.take() {
-            // Getting a character not in the alphabet is a hard error
-            let char_index = *self
-                .alphabet
-                .get(&letter)
-                .unwrap_or_else(|| std::process::exit(42));
+            // A character not in the alphabet leads to E, the driver reports it
+            let char_index = match self.alphabet.get(&letter) {
+                Some(&char_index) => char_index,
+                None => return None,
+            };
             let new_state = self.dfa.transition(current_state, char_index);
 
+            // length is in bytes, which is how the driver slices the source
             if new_state.is_some() {
-                self.length.set(self.length.get() + 1);
+                self.length.set(self.length.get() + letter.len_utf8());
+                if let Some(visits) = self.visits.borrow_mut().as_mut() {
+                    *visits.entry((current_state, char_index)).or_insert(0) += 1;
+                }
             }
 
             self.state.set(new_state);
@@ -71,33 +94,71 @@ impl<'d, 'a, 't> Regex<'d, 'a, 't> {
 
     // returns the length of the longest match
     pub fn first_match(&self, input: &str, newline: char) -> (usize, usize, usize) {
-        let mut chars = input.chars();
+        self.find_match(input, newline, false, false).0
+    }
+
+    // returns the length of the shortest non-empty match
+    pub fn shortest_match(&self, input: &str, newline: char) -> (usize, usize, usize) {
+        self.find_match(input, newline, true, false).0
+    }
+
+    /// The longest or shortest match, with its length in chars and how many
+    /// chars the DFA takes before running into E, None if it takes all of
+    /// them. Reads the input once.
+    pub fn traced_match(&self, input: &str, newline: char, shortest: bool) -> Traced {
+        self.find_match(input, newline, shortest, true)
+    }
+
+    /// With to_death, carries on past a shortest match to find the run
+    fn find_match(&self, input: &str, newline: char, shortest: bool, to_death: bool) -> Traced {
         let mut length = 0;
         let mut num_newlines = 0;
         let mut position = 1;
         let mut final_pos = 1;
         let mut newlines = 0;
+        let mut chars = 0;
+        let mut run = None;
 
-        while let Some(letter) = chars.next() {
+        for (taken, letter) in input.chars().enumerate() {
             if let Some(next_state) = self.accept(letter) {
                 position += 1;
                 if letter == newline {
                     newlines += 1;
                     position = 1;
                 }
-                if self.does_accept(next_state) {
+                if self.does_accept(next_state) && !(shortest && length > 0) {
                     length = self.len();
                     num_newlines += newlines;
                     final_pos = position;
                     newlines = 0;
+                    chars = taken + 1;
+                    if shortest && !to_death {
+                        break;
+                    }
                 }
             } else {
+                run = Some(taken);
+                break;
+            }
+        }
+        self.reset();
+
+        ((length, num_newlines, final_pos), chars, run)
+    }
+
+    /// How many chars of the input the DFA takes before running into E,
+    /// None if it takes all of them
+    pub fn run_length(&self, input: &str) -> Option<usize> {
+        let mut run = None;
+        for (taken, letter) in input.chars().enumerate() {
+            if self.accept(letter).is_none() {
+                run = Some(taken);
                 break;
             }
         }
         self.reset();
 
-        (length, num_newlines, final_pos)
+        run
     }
 
     pub fn full_match(&self, input: &str) -> bool {
@@ -186,6 +247,19 @@ pub mod tests {
         assert_eq!(regex.first_match("r", '\n'), (0, 0, 0));
     }
 
+    #[test]
+    fn shortest_endsq() {
+        let alpha = Alphabet::from_iter(ALPHABET.iter().copied());
+        let dfa = DFA::from_file("./wiki/endsq.tt").unwrap();
+
+        let regex = Regex::new(&dfa, None, &alpha, None);
+
+        assert_eq!(regex.shortest_match("pqrq", '\n'), (2, 0, 3));
+        assert_eq!(regex.first_match("pqrq", '\n'), (4, 0, 5));
+        assert_eq!(regex.shortest_match("p\nq\nq", '\n'), (3, 1, 2));
+        assert_eq!(regex.shortest_match("prs", '\n'), (0, 0, 1));
+    }
+
     #[test]
     fn twosmallwords() {
         let alpha = Alphabet::from_iter(ALPHABET.iter().copied());
@@ -202,4 +276,23 @@ pub mod tests {
         assert_eq!(regex.first_match("p", '\n'), (0, 0, 0));
         assert_eq!(regex.first_match("q p", '\n'), (0, 0, 0));
     }
+
+    #[test]
+    fn wiki_covering_strings() {
+        let alpha = Alphabet::from_iter(ALPHABET.iter().copied());
+        for file in [
+            "noto",
+            "nots",
+            "endsq",
+            "twosmallwords",
+            "whackamole",
+            "anyone",
+        ] {
+            let dfa = DFA::from_file(format!("./wiki/{}.tt", file)).unwrap();
+            let regex = Regex::new(&dfa, None, &alpha, None);
+            for (text, accepted) in dfa.covering_strings(&alpha) {
+                assert_eq!(regex.full_match(&text), accepted, "{} on {:?}", file, text);
+            }
+        }
+    }
 }
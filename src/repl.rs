@@ -0,0 +1,128 @@
+use crate::alphabet_translator::alphabet_translator;
+use crate::driver::Driver;
+use crate::regex::Regex;
+use crate::scanner::Scanner;
+use std::io::{BufRead, Write};
+
+// Each line typed is scanned on its own, with xHH for special chars.
+// :step    turns printing each char, its column and the row reached on or off
+// :quit    leaves, as does the end of the input
+
+/// Reads lines from input until :quit and writes what the scanner makes of them
+pub fn run(scanner: &Scanner, input: impl BufRead, mut output: impl Write) -> std::io::Result<()> {
+    let mut step = false;
+    write!(output, "> ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        match line.trim() {
+            ":quit" => break,
+            ":step" => {
+                step = !step;
+                let state = if step { "on" } else { "off" };
+                writeln!(output, "step mode {}", state)?;
+            }
+            _ => report(
+                scanner,
+                &alphabet_translator::hex_a_string_to_string(&line),
+                step,
+                &mut output,
+            )?,
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+    writeln!(output)
+}
+
+/// How each transition table matches the text, and the token the driver picks
+pub fn report(
+    scanner: &Scanner,
+    text: &str,
+    step: bool,
+    output: &mut impl Write,
+) -> std::io::Result<()> {
+    let alpha = scanner.get_alpha();
+    if let Some(letter) = text.chars().find(|c| !alpha.contains_key(c)) {
+        let letter = alphabet_translator::readable_hex_a_string(&letter.to_string());
+        return writeln!(output, "\"{}\" is not in the alphabet", letter);
+    }
+
+    for t in scanner.get_trans().iter() {
+        let regex = Regex::new(&t.tt, Some(&t.id), &alpha, t.replace_with.clone());
+        let (length, _, _) = regex.first_match(text, scanner.newline);
+        let full = if regex.full_match(text) { "yes" } else { "no" };
+        writeln!(
+            output,
+            "{}: first match {}, full match {}",
+            t.id, length, full
+        )?;
+
+        if step {
+            let mut state = if t.tt.states() > 0 { Some(0) } else { None };
+            for letter in text.chars() {
+                let column = alpha[&letter];
+                state = state.and_then(|s| t.tt.next(s, column));
+                let reached = match state {
+                    Some(s) if t.tt.is_accepting(s) => format!("row {} accepting", s),
+                    Some(s) => format!("row {}", s),
+                    None => "E".to_string(),
+                };
+                writeln!(
+                    output,
+                    "  {} column {} -> {}",
+                    alphabet_translator::readable_hex_a_string(&letter.to_string()),
+                    column,
+                    reached
+                )?;
+                if state.is_none() {
+                    break;
+                }
+            }
+        }
+    }
+
+    let tokens = Driver::tokenize(scanner, text).unwrap_or_else(|e| e.tokens);
+    match tokens.first() {
+        Some(token) => writeln!(output, "token: {}", token),
+        None => writeln!(output, "token: none"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn session(input: &str) -> String {
+        let scanner = Scanner::from_file("wiki/scan.u").unwrap();
+        let mut output = vec![];
+        run(&scanner, input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn report_each_table() {
+        let output = session("pqo\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "> pqrs: first match 2, full match no");
+        assert_eq!(lines[1], "opqr: first match 3, full match yes");
+        assert_eq!(lines[6], "token: opqr x70x71x6F 1 1");
+    }
+
+    #[test]
+    fn step_mode() {
+        let output = session(":step\nsx6f\n:step\n:quit\np\n");
+        assert!(output.starts_with("> step mode on\n> pqrs: first match 1, full match no\n"));
+        assert!(output.contains("  s column 7 -> row 1 accepting\n  o column 3 -> E\n"));
+        assert!(output.contains("step mode off"));
+        // nothing after :quit is scanned
+        assert_eq!(output.matches("token:").count(), 1);
+    }
+
+    #[test]
+    fn outside_the_alphabet() {
+        let output = session("pzq\n");
+        assert!(output.contains("\"z\" is not in the alphabet"));
+        assert!(!output.contains("token:"));
+    }
+}
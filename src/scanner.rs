@@ -1,6 +1,8 @@
 use crate::alphabet_translator::alphabet_translator;
 use crate::dfa::*;
-use std::collections::BTreeMap;
+use crate::driver::MatchPolicy;
+use crate::expr::Expr;
+use crate::keywords::KeywordTable;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
@@ -12,20 +14,94 @@ use std::path::Path;
 // wiki/twosmallwords.tt  twosmallwords
 // wiki/whackamole.tt     whack         x5cooox5cx20x5cooox5c
 // wiki/anyone.tt         IGNORE
-
-type Alphabet = BTreeMap<char, usize>;
+// wiki/noto.tt&!tests/pq.tt  notpq
+//
+// A line starting with % is a directive for the transition table above it
+// %keywords tests/keywords.kw
+// %priority 2
+// or for the whole scanner
+// %policy first-defined
+//
+// [:other:] in the alphabet line is a column for every char not listed,
+// [a-z] is a column for each char from a to z (spell a - inside it x2D),
+// {DIGIT=0-9} is one column shared by all the chars it lists
+
+pub use crate::alphabet::Alphabet;
 type State = usize;
 
+const OTHER: &str = "[:other:]";
+
+/// A piece of the alphabet line, before ranges are put together
+enum Entry {
+    Letter(char),
+    Dash,
+    Class(String),
+    Named(String, Vec<Entry>),
+    Other,
+}
+
+/// The NAME of a [:NAME:] class the text starts with, if it is one we know.
+/// Anything else is just a [ and the chars after it.
+fn known_class(text: &str) -> Option<&str> {
+    let rest = text.strip_prefix("[:")?;
+    let name = &rest[..rest.find(":]")?];
+    if name == "other" || Alphabet::class(name).is_some() {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// The inside of a [a-z] range the text starts with. Outside brackets a -
+/// is just a -, so alphabets like x0a()+-/* keep their columns.
+fn range_group(text: &str) -> Option<&str> {
+    let rest = text.strip_prefix('[')?;
+    let inside = &rest[..rest.find(']')?];
+    let dash = inside.find('-')?;
+    if dash > 0 && dash + 1 < inside.len() {
+        Some(inside)
+    } else {
+        None
+    }
+}
+
+/// The chars of a column, None for [:other:], and how they were given
+type Column = (Option<Vec<char>>, String);
+
+/// The NAME of a {NAME=...} class the text starts with. A { that isn't
+/// followed by a name and = is just a {, as it was before classes.
+fn class_name(text: &str) -> Option<&str> {
+    let rest = text.strip_prefix('{')?;
+    let length = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    let name = &rest[..length];
+    let starts_well = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+    if starts_well && rest[length..].starts_with('=') {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+fn readable(letter: char) -> String {
+    alphabet_translator::readable_hex_a_string(&letter.to_string())
+}
+
 /// Each line of the sanner definition file looks like
 /// wiki/noto.tt           pqrs         replace_with
-/// tt is the dfa created by the file name
+/// tt is the dfa created by the file name, or by an expression over file names
 /// id is the name in the middle
 /// replace_with is the optional replace with value
+/// keywords is the optional keyword table from a %keywords directive
+/// priority breaks ties between matches, higher wins, from a %priority directive
 #[derive(Debug)]
 pub struct TransitionTable {
     pub tt: DFA, // TODO should probably make getters for these instead of making them pub
     pub id: String,
     pub replace_with: Option<String>,
+    pub keywords: Option<KeywordTable>,
+    pub priority: i32,
 }
 
 impl TransitionTable {
@@ -34,16 +110,19 @@ impl TransitionTable {
             tt,
             id,
             replace_with,
+            keywords: None,
+            priority: 0,
         }
     }
-    pub fn from_str_custom(input: &str) -> Result<Self, ()> {
+    /// Reads a definition line, complementing over the alphabet's columns
+    pub fn from_str_custom(input: &str, columns: usize) -> Result<Self, ()> {
         let tokens: Vec<&str> = input.trim().split_whitespace().collect();
 
         match tokens.as_slice() {
             [file_name, id] => {
                 // if replace_with.
                 Ok(TransitionTable::new(
-                    DFA::from_file(file_name).unwrap_or_else(|_| std::process::exit(2)),
+                    Expr::load(file_name, columns).unwrap_or_else(|_| std::process::exit(2)),
                     id.to_string(),
                     None,
                 ))
@@ -51,7 +130,7 @@ impl TransitionTable {
             [file_name, id, replace_with] => {
                 // if replace_with.
                 Ok(TransitionTable::new(
-                    DFA::from_file(file_name).unwrap_or_else(|_| std::process::exit(3)),
+                    Expr::load(file_name, columns).unwrap_or_else(|_| std::process::exit(3)),
                     id.to_string(),
                     Some(replace_with.to_string()),
                 ))
@@ -61,19 +140,44 @@ impl TransitionTable {
     }
 }
 
+/// A char of a source that is not in the alphabet, how often it appears and
+/// the line and column it first appears at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownChar {
+    pub letter: char,
+    pub count: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// What merging the columns every table treats the same did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compression {
+    pub columns: usize,
+    pub classes: usize,
+    pub bytes_before: usize,
+    pub bytes_after: usize,
+}
+
 /// Main struct for a scan definition file.
 pub struct Scanner {
     pub newline: char,
+    pub policy: MatchPolicy,
     alpha: Alphabet,
     transition_tables: Vec<TransitionTable>,
+    warnings: Vec<String>,
+    compression: Option<Compression>,
 }
 
 impl Scanner {
     pub fn new(newline: char, alpha: Alphabet, transition_tables: Vec<TransitionTable>) -> Self {
         Self {
             newline,
+            policy: MatchPolicy::default(),
             alpha,
             transition_tables,
+            warnings: vec![],
+            compression: None,
         }
     }
     pub fn get_alpha(&self) -> Alphabet {
@@ -82,6 +186,160 @@ impl Scanner {
     pub fn get_trans(&self) -> &Vec<TransitionTable> {
         &self.transition_tables
     }
+
+    /// What `analyze` found worth a warning when the definition was read
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Turns a word of alphabet columns back into the text it stands for,
+    /// with xHH for special chars and [:other:] for the other column.
+    pub fn word_to_string(&self, word: &[usize]) -> String {
+        word.iter()
+            .filter_map(|&column| {
+                if self.alpha.other() == Some(column) {
+                    return Some(OTHER.to_string());
+                }
+                self.alpha
+                    .iter()
+                    .find(|(_, &c)| c == column)
+                    .map(|(letter, _)| {
+                        alphabet_translator::readable_hex_a_string(&letter.to_string())
+                    })
+            })
+            .collect()
+    }
+
+    /// Reads a scanning definition and compresses it, the scanner every
+    /// command that scans text uses
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut scanner = Scanner::from_file(path)?;
+        scanner.compression = Some(scanner.compress());
+        Ok(scanner)
+    }
+
+    /// What compressing the scanner saved, if it was loaded compressed
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// Merges the columns that every table treats the same into one, and
+    /// points the alphabet at the merged columns, so each text still scans
+    /// the same way with smaller tables.
+    pub fn compress(&mut self) -> Compression {
+        let columns = self
+            .transition_tables
+            .iter()
+            .map(|t| t.tt.columns())
+            .chain(std::iter::once(self.alpha.columns()))
+            .max()
+            .unwrap_or(0);
+        let bytes_before = self.table_bytes();
+
+        // what every row of every table does on a column
+        let signature = |column: usize| -> Vec<Option<usize>> {
+            self.transition_tables
+                .iter()
+                .flat_map(|t| (0..t.tt.states()).map(move |row| t.tt.next(row, column)))
+                .collect()
+        };
+        let mut signatures = vec![];
+        let mut kept = vec![];
+        let class_of: Vec<usize> = (0..columns)
+            .map(|column| {
+                let s = signature(column);
+                match signatures.iter().position(|other| *other == s) {
+                    Some(class) => class,
+                    None => {
+                        signatures.push(s);
+                        kept.push(column);
+                        kept.len() - 1
+                    }
+                }
+            })
+            .collect();
+
+        let mut alpha: Alphabet = self
+            .alpha
+            .iter()
+            .map(|(&letter, &column)| (letter, class_of[column]))
+            .collect();
+        if let Some(other) = self.alpha.other() {
+            alpha.set_other(class_of[other]);
+        }
+        self.alpha = alpha;
+        for t in self.transition_tables.iter_mut() {
+            t.tt = t.tt.select_columns(&kept);
+        }
+
+        Compression {
+            columns,
+            classes: kept.len(),
+            bytes_before,
+            bytes_after: self.table_bytes(),
+        }
+    }
+
+    /// How much memory the transition tables take
+    pub fn table_bytes(&self) -> usize {
+        self.transition_tables
+            .iter()
+            .map(|t| t.tt.table_bytes())
+            .sum()
+    }
+
+    /// Every char of the source missing from the alphabet, in the order they
+    /// first appear
+    pub fn unknown_chars(&self, source: &str) -> Vec<UnknownChar> {
+        let mut found: Vec<UnknownChar> = vec![];
+        let mut line = 1;
+        let mut column = 1;
+        for letter in source.chars() {
+            if !self.alpha.contains_key(&letter) {
+                match found.iter_mut().find(|u| u.letter == letter) {
+                    Some(unknown) => unknown.count += 1,
+                    None => found.push(UnknownChar {
+                        letter,
+                        count: 1,
+                        line,
+                        column,
+                    }),
+                }
+            }
+            if letter == self.newline {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        found
+    }
+
+    /// The source with every char missing from the alphabet replaced by
+    /// catch_all, which has to be in it
+    pub fn map_unknown_chars(&self, source: &str, catch_all: char) -> Result<String, String> {
+        if !self.alpha.contains_key(&catch_all) {
+            return Err(format!(
+                "{} is not in the alphabet",
+                alphabet_translator::char_to_hex(catch_all)
+            ));
+        }
+        Ok(source
+            .chars()
+            .map(|c| {
+                if self.alpha.contains_key(&c) {
+                    c
+                } else {
+                    catch_all
+                }
+            })
+            .collect())
+    }
+
+    /// Reads a scanning definition. The tables keep the columns of the
+    /// alphabet line, which analyses comparing tables column by column rely
+    /// on. Scan with `load` instead.
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
         let file = File::open(path)?;
         let reader = BufReader::new(file);
@@ -90,47 +348,207 @@ impl Scanner {
         // Empty Scanners are errors.
         let first_line = all_rows.next().unwrap_or_else(|| std::process::exit(5));
 
-        let (alphabet, newline) = Scanner::alphabet_build(&first_line);
+        let (alphabet, newline) = Scanner::alphabet_build(&first_line)?;
 
-        let mut tts: Vec<TransitionTable> = all_rows
-            .map(|r| TransitionTable::from_str_custom(&r))
-            .flatten()
-            .collect();
+        let mut scanner = Scanner::new(newline, alphabet, Vec::new());
+        for row in all_rows {
+            if row.trim_start().starts_with('%') {
+                scanner.apply_directive(&row)?;
+            } else if let Ok(tt) = TransitionTable::from_str_custom(&row, scanner.alpha.columns()) {
+                scanner.transition_tables.push(tt);
+            }
+        }
+        scanner.warnings = scanner.analyze()?;
+
+        Ok(scanner)
+    }
+
+    /// Tables that can never produce a token are refused, tables that match
+    /// the empty string only get a warning since the driver skips empty
+    /// matches. Returns the warnings.
+    fn analyze(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut warnings = vec![];
+        for t in self.transition_tables.iter() {
+            if t.tt.is_empty() || t.tt.max_length() == Some(0) {
+                return Err(format!("{} never matches any text", t.id).into());
+            }
+            if t.tt.accepts_empty() {
+                warnings.push(format!("{} matches the empty string", t.id));
+            }
+        }
+        Ok(warnings)
+    }
+
+    /// Directives modify the whole scanner or the most recently defined
+    /// transition table
+    fn apply_directive(&mut self, input: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let tokens: Vec<&str> = input.split_whitespace().collect();
+
+        match tokens.as_slice() {
+            ["%policy", policy] => {
+                self.policy = policy.parse()?;
+                return Ok(());
+            }
+            [_, _] => (),
+            _ => return Err(format!("invalid directive: {}", input).into()),
+        }
+
+        let last = self
+            .transition_tables
+            .last_mut()
+            .ok_or_else(|| format!("directive before any transition table: {}", input))?;
+        match tokens.as_slice() {
+            ["%keywords", file_name] => last.keywords = Some(KeywordTable::from_file(file_name)?),
+            ["%priority", priority] => last.priority = priority.parse()?,
+            _ => return Err(format!("unknown directive: {}", input).into()),
+        }
 
-        Ok(Scanner::new(newline, alphabet, tts))
+        Ok(())
     }
 
     /// Alphabet comes in with xHH for control chars, we need
-    /// to turn it into real chars
-    fn alphabet_build(input: &str) -> (Alphabet, char) {
-        let mut alpha = Alphabet::new();
+    /// to turn it into real chars. [a-z] and [x20-x7E] are ranges, a - outside
+    /// brackets is just a -, and [:digit:] and the like are named classes. Each char gets its own column, in the order given,
+    /// except for {NAME=...} which puts all the chars it lists in one column.
+    /// The newline char is the first one written as xHH.
+    pub fn alphabet_build(input: &str) -> Result<(Alphabet, char), Box<dyn std::error::Error>> {
         let mut clean_in = String::from(input);
         clean_in.retain(|c| !c.is_whitespace());
-
         let in_chars: Vec<char> = clean_in.chars().collect();
-        let mut chars = vec![];
-        let mut char_index = 0;
+
+        let mut newline = None;
+        let entries = Scanner::alphabet_entries(&in_chars, &mut newline, false)?;
+
+        let mut alpha = Alphabet::new();
+        for (char_index, (letters, given)) in
+            Scanner::alphabet_columns(&entries)?.into_iter().enumerate()
+        {
+            let letters = match letters {
+                Some(letters) => letters,
+                None if alpha.other().is_some() => {
+                    return Err(format!("{} is in the alphabet more than once", OTHER).into())
+                }
+                None => {
+                    alpha.set_other(char_index);
+                    continue;
+                }
+            };
+            for letter in letters {
+                if alpha.insert(letter, char_index).is_some() {
+                    return Err(format!(
+                        "{} is in the alphabet more than once, again in {}",
+                        readable(letter),
+                        given
+                    )
+                    .into());
+                }
+            }
+        }
+
+        let newline = newline.ok_or("the alphabet has no newline char written as xHH")?;
+        Ok((alpha, newline))
+    }
+
+    /// Splits the alphabet line, or the inside of a {NAME=...} or [a-z], into
+    /// entries. A - only makes a range inside those.
+    fn alphabet_entries(
+        in_chars: &[char],
+        newline: &mut Option<char>,
+        ranges: bool,
+    ) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+        let mut entries = vec![];
         let mut i = 0;
         while i < in_chars.len() {
-            if in_chars[i] == 'x' {
-                let hex_str: String = in_chars[i + 1..=i + 2].iter().collect();
-                chars.push(alphabet_translator::hex_to_char(&hex_str));
-                alpha.insert(alphabet_translator::hex_to_char(&hex_str), char_index);
-                i += 2; // Skip the next two hex characters
+            let rest: String = in_chars[i..].iter().collect();
+            if let Some(name) = known_class(&rest) {
+                entries.push(if name == "other" {
+                    Entry::Other
+                } else {
+                    Entry::Class(name.to_string())
+                });
+                i += name.chars().count() + 4;
+            } else if let Some(inside) = range_group(&rest).filter(|_| !ranges) {
+                let spec: Vec<char> = inside.chars().collect();
+                entries.extend(Scanner::alphabet_entries(&spec, newline, true)?);
+                i += spec.len() + 2;
+            } else if let Some(name) = class_name(&rest) {
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| format!("unclosed class in the alphabet: {}", rest))?;
+                let spec: Vec<char> = rest[name.len() + 2..end].chars().collect();
+                entries.push(Entry::Named(
+                    name.to_string(),
+                    Scanner::alphabet_entries(&spec, newline, true)?,
+                ));
+                i += rest[..=end].chars().count();
+            } else if in_chars[i] == 'x' {
+                let hex_str: String = in_chars[i + 1..].iter().take(2).collect();
+                if hex_str.len() != 2 || !hex_str.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(format!("x{} in the alphabet is not xHH", hex_str).into());
+                }
+                let letter = alphabet_translator::hex_to_char(&hex_str);
+                newline.get_or_insert(letter);
+                entries.push(Entry::Letter(letter));
+                i += 3;
+            } else if in_chars[i] == '-' && ranges {
+                entries.push(Entry::Dash);
+                i += 1;
             } else {
-                alpha.insert(in_chars[i], char_index);
+                entries.push(Entry::Letter(in_chars[i]));
+                i += 1;
             }
-            char_index += 1;
-            i += 1;
         }
+        Ok(entries)
+    }
 
-        (alpha, chars[0])
+    /// The columns the entries make
+    fn alphabet_columns(entries: &[Entry]) -> Result<Vec<Column>, Box<dyn std::error::Error>> {
+        let mut columns = vec![];
+        let mut i = 0;
+        while i < entries.len() {
+            match (&entries[i], entries.get(i + 1), entries.get(i + 2)) {
+                (Entry::Letter(first), Some(Entry::Dash), Some(Entry::Letter(last))) => {
+                    let given = format!("{}-{}", readable(*first), readable(*last));
+                    if first > last {
+                        return Err(format!("range {} in the alphabet is backwards", given).into());
+                    }
+                    columns.extend((*first..=*last).map(|c| (Some(vec![c]), given.clone())));
+                    i += 2;
+                }
+                (Entry::Letter(letter), _, _) => {
+                    columns.push((Some(vec![*letter]), readable(*letter)))
+                }
+                (Entry::Dash, _, _) => columns.push((Some(vec!['-']), "-".to_string())),
+                (Entry::Class(name), _, _) => {
+                    let given = format!("[:{}:]", name);
+                    let letters = Alphabet::class(name)
+                        .ok_or_else(|| format!("unknown class in the alphabet: {}", given))?;
+                    columns.extend(letters.into_iter().map(|c| (Some(vec![c]), given.clone())));
+                }
+                (Entry::Named(name, inner), _, _) => {
+                    let mut letters = vec![];
+                    for (inner, _) in Scanner::alphabet_columns(inner)? {
+                        let inner =
+                            inner.ok_or_else(|| format!("{} can't be in class {}", OTHER, name))?;
+                        letters.extend(inner);
+                    }
+                    if letters.is_empty() {
+                        return Err(format!("class {} has no chars", name).into());
+                    }
+                    columns.push((Some(letters), format!("{{{}}}", name)));
+                }
+                (Entry::Other, _, _) => columns.push((None, OTHER.to_string())),
+            }
+            i += 1;
+        }
+        Ok(columns)
     }
 }
 
 #[cfg(test)]
 mod test {
     use crate::dfa::*;
+    use crate::driver::Driver;
     use crate::scanner::*;
 
     // loading from file
@@ -162,6 +580,128 @@ mod test {
         );
     }
 
+    #[test]
+    fn load_other_column() {
+        let sc = Scanner::from_file("tests/other.u").unwrap();
+        assert_eq!(sc.alpha.other(), Some(8));
+        assert_eq!(sc.alpha.columns(), 9);
+        assert_eq!(sc.newline, '\n');
+        assert_eq!(sc.unknown_chars("pq$ ü"), vec![]);
+        assert_eq!(sc.word_to_string(&[4, 8, 1]), "p[:other:]x20");
+    }
+
+    #[test]
+    fn compress_wiki() {
+        let mut sc = Scanner::from_file("wiki/scan.u").unwrap();
+        let source = std::fs::read_to_string("wiki/source.txt").unwrap();
+        let tokens = Driver::tokenize(&sc, &source).unwrap();
+
+        let compression = sc.compress();
+        assert_eq!((compression.columns, compression.classes), (8, 7));
+        assert_eq!(compression.bytes_after, compression.bytes_before / 8 * 7);
+        assert_eq!(Driver::tokenize(&sc, &source).unwrap(), tokens);
+        // compressing again changes nothing
+        let again = sc.compress();
+        assert_eq!(again.columns, again.classes);
+        assert_eq!(again.bytes_before, again.bytes_after);
+    }
+
+    #[test]
+    fn load_compresses() {
+        let sc = Scanner::load("tests/classes.u").unwrap();
+        let plain = Scanner::from_file("tests/classes.u").unwrap();
+        assert!(sc.alpha.columns() < plain.alpha.columns());
+        assert!(sc.table_bytes() < plain.table_bytes());
+        let saved = sc.compression().unwrap();
+        assert_eq!(saved.bytes_before, plain.table_bytes());
+        assert_eq!(saved.bytes_after, sc.table_bytes());
+        assert_eq!(plain.compression(), None);
+        let source = "x1 42\nSum_3 007";
+        assert_eq!(
+            Driver::tokenize(&sc, source).unwrap(),
+            Driver::tokenize(&plain, source).unwrap()
+        );
+    }
+
+    #[test]
+    fn compress_merges_columns() {
+        let mut sc = Scanner::from_file("tests/classes.u").unwrap();
+        sc.transition_tables.truncate(1);
+        // number only tells digits apart from the rest
+        assert_eq!(
+            sc.compress(),
+            Compression {
+                columns: 4,
+                classes: 2,
+                bytes_before: 2 * 4 * 4,
+                bytes_after: 2 * 2 * 4,
+            }
+        );
+        assert_eq!(sc.alpha[&'7'], sc.alpha[&'0']);
+        assert_eq!(sc.alpha[&'a'], sc.alpha[&'\n']);
+        assert_ne!(sc.alpha[&'a'], sc.alpha[&'7']);
+        assert_eq!(sc.transition_tables[0].tt.columns(), 2);
+        let tokens = Driver::tokenize(&sc, "42a").unwrap_err().tokens;
+        assert_eq!(tokens.len(), 1);
+    }
+
+    #[test]
+    fn unknown_chars_with_counts() {
+        let sc = Scanner::from_file("wiki/scan.u").unwrap();
+        assert_eq!(sc.unknown_chars("pq rs\n"), vec![]);
+        assert_eq!(
+            sc.unknown_chars("pq$\nr$ \t$"),
+            vec![
+                UnknownChar {
+                    letter: '$',
+                    count: 3,
+                    line: 1,
+                    column: 3
+                },
+                UnknownChar {
+                    letter: '\t',
+                    count: 1,
+                    line: 2,
+                    column: 4
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn map_unknown_chars_to_catch_all() {
+        let sc = Scanner::from_file("wiki/scan.u").unwrap();
+        assert_eq!(
+            sc.map_unknown_chars("pq$\nr$ \t", ' '),
+            Ok("pq \nr   ".to_string())
+        );
+        assert!(sc.map_unknown_chars("pq$", '$').is_err());
+    }
+
+    #[test]
+    fn refuses_tables_that_never_match() {
+        assert!(Scanner::from_file("tests/never_matches.u").is_err());
+        let mut sc = Scanner::new('\n', Alphabet::new(), vec![]);
+        sc.transition_tables
+            .push(TransitionTable::from_str_custom("tests/empty_string.tt empty", 0).unwrap());
+        assert!(sc.analyze().is_err());
+    }
+
+    #[test]
+    fn allows_tables_that_match_the_empty_string() {
+        let mut sc = Scanner::new('\n', Alphabet::new(), vec![]);
+        sc.transition_tables
+            .push(TransitionTable::from_str_custom("tests/optional_a.tt a", 0).unwrap());
+        assert_eq!(
+            sc.analyze().unwrap(),
+            vec!["a matches the empty string".to_string()]
+        );
+        assert!(Scanner::from_file("wiki/scan.u")
+            .unwrap()
+            .warnings()
+            .is_empty());
+    }
+
     #[test]
     #[should_panic]
     fn bad_scanner_file() {
@@ -174,28 +714,75 @@ mod test {
         let sc = Scanner::from_file("tests/empty_file.tt").unwrap();
     }
 
+    #[test]
+    fn keywords_directive() {
+        let sc = Scanner::from_file("tests/keywords.u").unwrap();
+        assert_eq!(sc.transition_tables.len(), 2);
+        assert_eq!(
+            sc.transition_tables[0].keywords,
+            Some(KeywordTable::from_file("tests/keywords.kw").unwrap())
+        );
+        assert_eq!(sc.transition_tables[1].keywords, None);
+    }
+
+    #[test]
+    fn priority_and_policy_directives() {
+        let sc = Scanner::from_file("tests/priorities.u").unwrap();
+        assert_eq!(sc.policy, MatchPolicy::FirstDefined);
+        let priorities: Vec<i32> = sc.transition_tables.iter().map(|t| t.priority).collect();
+        assert_eq!(priorities, vec![0, 2, -1]);
+    }
+
+    #[test]
+    fn directive_before_table() {
+        let mut sc = Scanner::new('\n', Alphabet::new(), vec![]);
+        assert!(sc.apply_directive("%keywords tests/keywords.kw").is_err());
+        assert!(sc.apply_directive("%policy shortest").is_ok());
+        assert_eq!(sc.policy, MatchPolicy::Shortest);
+    }
+
+    #[test]
+    fn invalid_directives() {
+        let mut sc = Scanner::new('\n', Alphabet::new(), vec![]);
+        sc.transition_tables
+            .push(TransitionTable::from_str_custom("wiki/nots.tt opqr", 0).unwrap());
+        assert!(sc.apply_directive("%nonsense").is_err());
+        assert!(sc.apply_directive("%nonsense 1").is_err());
+        assert!(sc.apply_directive("%priority high").is_err());
+        assert!(sc.apply_directive("%policy longest").is_err());
+    }
+
+    #[test]
+    fn words_to_strings() {
+        let sc = Scanner::from_file("wiki/scan.u").unwrap();
+        assert_eq!(sc.word_to_string(&[4, 5, 1, 0, 2]), "pqx20x0A\\");
+        assert_eq!(sc.word_to_string(&[]), "");
+    }
+
     // alphabet builder
 
     #[test]
     fn one_char_alphabet() {
         let mut b = Alphabet::new();
-        b.insert('a', 0);
-        assert_eq!(Scanner::alphabet_build("a").0, b);
+        b.insert('\n', 0);
+        b.insert('a', 1);
+        assert_eq!(Scanner::alphabet_build("x0aa").unwrap().0, b);
     }
 
     #[test]
     fn two_char_alphabet() {
         let mut b = Alphabet::new();
-        b.insert('a', 0);
-        b.insert('b', 1);
-        assert_eq!(Scanner::alphabet_build("ab").0, b);
+        b.insert('\n', 0);
+        b.insert('a', 1);
+        b.insert('b', 2);
+        assert_eq!(Scanner::alphabet_build("x0aab").unwrap().0, b);
     }
 
     #[test]
     fn new_line_alphabet() {
         let mut b = Alphabet::new();
         b.insert('\n', 0);
-        assert_eq!(Scanner::alphabet_build("x0a").0, b);
+        assert_eq!(Scanner::alphabet_build("x0a").unwrap().0, b);
     }
 
     #[test]
@@ -204,7 +791,7 @@ mod test {
         b.insert('\n', 0);
         b.insert(' ', 1);
         b.insert('\\', 2);
-        assert_eq!(Scanner::alphabet_build("x0ax20x5C"), (b, '\n'));
+        assert_eq!(Scanner::alphabet_build("x0ax20x5C").unwrap(), (b, '\n'));
     }
 
     #[test]
@@ -220,7 +807,151 @@ mod test {
         b.insert('q', 5);
         b.insert('r', 6);
         b.insert('s', 7);
-        assert_eq!(Scanner::alphabet_build("x0ax20x5C x6fpqrx73"), (b, '\n'));
+        assert_eq!(
+            Scanner::alphabet_build("x0ax20x5C x6fpqrx73").unwrap(),
+            (b, '\n')
+        );
+    }
+
+    #[test]
+    fn alphabet_ranges() {
+        let (a, newline) = Scanner::alphabet_build("x0ax20x5C [o-s]").unwrap();
+        assert_eq!(a, Scanner::alphabet_build("x0ax20x5C x6fpqrx73").unwrap().0);
+        assert_eq!(newline, '\n');
+
+        let (a, _) = Scanner::alphabet_build("x0a [x20-x7E]").unwrap();
+        assert_eq!(a.columns(), 96);
+        assert_eq!((a[&' '], a[&'~']), (1, 95));
+
+        // a - outside brackets is just a -
+        let (a, _) = Scanner::alphabet_build("-x0aab").unwrap();
+        assert_eq!((a[&'-'], a[&'\n'], a[&'a'], a[&'b']), (0, 1, 2, 3));
+        let (a, _) = Scanner::alphabet_build("x0aab-").unwrap();
+        assert_eq!((a[&'\n'], a[&'a'], a[&'b'], a[&'-']), (0, 1, 2, 3));
+        let (a, _) = Scanner::alphabet_build("x0aa-b").unwrap();
+        assert_eq!((a[&'a'], a[&'-'], a[&'b']), (1, 2, 3));
+        // and so is one spelled x2D inside them
+        let (a, _) = Scanner::alphabet_build("x0a[+x2D-/]").unwrap();
+        assert_eq!((a[&'+'], a[&'-'], a[&'.'], a[&'/']), (1, 2, 3, 4));
+        // brackets without a range in them are letters
+        let (a, _) = Scanner::alphabet_build("x0a[]ab").unwrap();
+        assert_eq!((a[&'['], a[&']'], a[&'a']), (1, 2, 3));
+        let (a, _) = Scanner::alphabet_build("x0a[-]").unwrap();
+        assert_eq!((a[&'['], a[&'-'], a[&']']), (1, 2, 3));
+    }
+
+    #[test]
+    fn alphabet_operators() {
+        // alphabets written before ranges keep their columns
+        let (a, _) = Scanner::alphabet_build("x0a()+-/*").unwrap();
+        assert_eq!(a.columns(), 7);
+        assert_eq!(
+            (a[&'('], a[&')'], a[&'+'], a[&'-'], a[&'/'], a[&'*']),
+            (1, 2, 3, 4, 5, 6)
+        );
+        assert_eq!(a.get(&','), None);
+        let (a, _) = Scanner::alphabet_build("x0a+-*").unwrap();
+        assert_eq!((a[&'+'], a[&'-'], a[&'*']), (1, 2, 3));
+    }
+
+    #[test]
+    fn alphabet_classes() {
+        let (a, _) = Scanner::alphabet_build("x0a[:digit:]_[:lower:][:other:]").unwrap();
+        assert_eq!(
+            (a[&'0'], a[&'9'], a[&'_'], a[&'a'], a[&'z']),
+            (1, 10, 11, 12, 37)
+        );
+        assert_eq!(a.other(), Some(38));
+
+        let (a, _) = Scanner::alphabet_build("x0a[:print:]").unwrap();
+        assert_eq!(a, Scanner::alphabet_build("x0a [x20-x7E]").unwrap().0);
+    }
+
+    #[test]
+    fn alphabet_errors() {
+        let error = |line: &str| Scanner::alphabet_build(line).unwrap_err().to_string();
+        assert_eq!(
+            error("x0aabca"),
+            "a is in the alphabet more than once, again in a"
+        );
+        assert_eq!(
+            error("x0a[:lower:][0-9][a-f]"),
+            "a is in the alphabet more than once, again in a-f"
+        );
+        assert_eq!(
+            error("x0ax20 [x0a-x0d]"),
+            "x0A is in the alphabet more than once, again in x0A-x0D"
+        );
+        assert_eq!(
+            error("x0a[:other:][:other:]"),
+            "[:other:] is in the alphabet more than once"
+        );
+        assert_eq!(error("x0a[z-a]"), "range z-a in the alphabet is backwards");
+        // [: that doesn't start a class we know is letters, and these repeat
+        assert_eq!(
+            error("x0a[:vowel:]"),
+            ": is in the alphabet more than once, again in :"
+        );
+        assert_eq!(
+            error("x0a[:num:"),
+            ": is in the alphabet more than once, again in :"
+        );
+        assert_eq!(error("x0axg1"), "xg1 in the alphabet is not xHH");
+        assert_eq!(
+            error("abc"),
+            "the alphabet has no newline char written as xHH"
+        );
+    }
+
+    #[test]
+    fn alphabet_unknown_classes() {
+        let (a, _) = Scanner::alphabet_build("x0a[:]").unwrap();
+        assert_eq!((a[&'['], a[&':'], a[&']']), (1, 2, 3));
+        let (a, _) = Scanner::alphabet_build("x0a[:a]").unwrap();
+        assert_eq!(a.columns(), 5);
+        assert_eq!((a[&'['], a[&':'], a[&'a'], a[&']']), (1, 2, 3, 4));
+        let (a, _) = Scanner::alphabet_build("x0a[:dig").unwrap();
+        assert_eq!(a.columns(), 6);
+    }
+
+    #[test]
+    fn alphabet_literal_braces() {
+        let (a, _) = Scanner::alphabet_build("x0ax20{}ab").unwrap();
+        assert_eq!((a[&'{'], a[&'}'], a[&'a'], a[&'b']), (2, 3, 4, 5));
+        // braces around something that isn't NAME= are letters too
+        let (a, _) = Scanner::alphabet_build("x0a{1=c}").unwrap();
+        assert_eq!(a.columns(), 6);
+        assert_eq!((a[&'{'], a[&'='], a[&'}']), (1, 3, 5));
+    }
+
+    #[test]
+    fn alphabet_named_classes() {
+        let (a, newline) =
+            Scanner::alphabet_build("x0a x20 {DIGIT=0-9} {LETTER=a-zA-Z_} - {REST=!#x09}").unwrap();
+        assert_eq!(newline, '\n');
+        assert_eq!(a.columns(), 6);
+        assert_eq!(
+            (a[&'0'], a[&'7'], a[&'b'], a[&'Q'], a[&'_']),
+            (2, 2, 3, 3, 3)
+        );
+        assert_eq!((a[&'-'], a[&'!'], a[&'\t']), (4, 5, 5));
+        // - in a class is a range, on its own it is a column of its own
+        assert_eq!(a.iter().filter(|(_, &c)| c == 4).count(), 1);
+
+        let error = |line: &str| Scanner::alphabet_build(line).unwrap_err().to_string();
+        assert_eq!(
+            error("x0a{DIGIT=0-9}{HEX=0-9a-f}"),
+            "0 is in the alphabet more than once, again in {HEX}"
+        );
+        assert_eq!(
+            error("x0a{ANY=[:other:]}"),
+            "[:other:] can't be in class ANY"
+        );
+        assert_eq!(error("x0a{NONE=}"), "class NONE has no chars");
+        assert_eq!(
+            error("x0a{DIGIT=0-9"),
+            "unclosed class in the alphabet: {DIGIT=0-9"
+        );
     }
 
     // hex to char
@@ -230,6 +961,7 @@ mod test {
     fn tt_from_str_with_replace() {
         let r = TransitionTable::from_str_custom(
             "wiki/whackamole.tt     whack         x5cooox5cx20x5cooox5c",
+            0,
         )
         .unwrap();
         assert_eq!(r.id, "whack".to_string());
@@ -240,28 +972,36 @@ mod test {
 
     #[test]
     fn tt_from_str_no_replace() {
-        let r = TransitionTable::from_str_custom("wiki/nots.tt           opqr").unwrap();
+        let r = TransitionTable::from_str_custom("wiki/nots.tt           opqr", 0).unwrap();
         assert_eq!(r.id, "opqr".to_string());
         assert_eq!(r.replace_with, None);
         assert_eq!(r.tt, DFA::from_file("wiki/nots.tt").unwrap());
     }
 
+    #[test]
+    fn tt_from_str_expression() {
+        let r = TransitionTable::from_str_custom("wiki/noto.tt&!tests/pq.tt  notpq", 0).unwrap();
+        let noto = DFA::from_file("wiki/noto.tt").unwrap();
+        let pq = DFA::from_file("tests/pq.tt").unwrap();
+        assert_eq!(r.tt.equivalent(&noto.difference(&pq)), Ok(()));
+    }
+
     #[test]
     #[should_panic]
     fn tt_from_str_invalid_file() {
-        let r =
-            TransitionTable::from_str_custom("this_file_does_not_exist.tt           opqr").unwrap();
+        let r = TransitionTable::from_str_custom("this_file_does_not_exist.tt           opqr", 0)
+            .unwrap();
     }
 
     #[test]
     #[should_panic]
     fn tt_from_empty_str() {
-        let r = TransitionTable::from_str_custom("").unwrap();
+        let r = TransitionTable::from_str_custom("", 0).unwrap();
     }
 
     // currently empty files do not panic... not sure if that is what we want
     #[test]
     fn tt_from_str_empty_file() {
-        let r = TransitionTable::from_str_custom("tests/empty_file.tt           opqr").unwrap();
+        let r = TransitionTable::from_str_custom("tests/empty_file.tt           opqr", 0).unwrap();
     }
 }